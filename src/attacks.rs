@@ -0,0 +1,6 @@
+pub mod dfa;
+pub mod malleability;
+pub mod nonce_reuse;
+pub mod padding_oracle;
+pub mod square;
+pub mod timing;
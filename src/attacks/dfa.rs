@@ -0,0 +1,247 @@
+use std::collections::HashSet;
+
+use crate::crypto::{aes, key_schedule};
+
+// Differential fault analysis of AES-128 (Piret and Quisquater).
+//
+// A byte of the state gets corrupted just before the MixColumns of round 9.
+// MixColumns spreads the error over one column as (2f, f, f, 3f) or a rotation
+// of it, and the last round only substitutes and moves those four bytes. So for
+// the right four last round key bytes, undoing the last round on a correct and
+// a faulty ciphertext has to give a difference of that shape. A couple of
+// faults per column leave a single candidate.
+
+// MixColumns matrix, column r is the difference pattern of a fault in row r
+const MIX: [[u8; 4]; 4] = [[2, 3, 1, 1], [1, 2, 3, 1], [1, 1, 2, 3], [3, 1, 1, 2]];
+
+// xor mask applied to a byte of the state just before the MixColumns of the
+// second to last round, position is in state order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault {
+    pub position: usize,
+    pub mask: u8,
+}
+
+// the victim device, encrypts normally or with a fault injected. AES-128
+// only, the attack recovers one round key and that is all of an AES-128 key.
+pub struct FaultInjector {
+    num_rounds: u8,
+    key_schedule: Vec<Vec<u8>>,
+}
+
+impl FaultInjector {
+    pub fn new(key: Vec<u8>) -> FaultInjector {
+        if key.len() != 16 {
+            panic!("Invalid key length");
+        }
+        FaultInjector {
+            num_rounds: aes::num_rounds(key.len() / 4),
+            key_schedule: aes::key_expansion(key),
+        }
+    }
+
+    pub fn encrypt(&self, input: Vec<u8>) -> Vec<u8> {
+        aes::cipher(input, self.num_rounds, &self.key_schedule)
+    }
+
+    // the same rounds as aes::cipher with the fault xored in along the way
+    pub fn encrypt_with_fault(&self, input: Vec<u8>, fault: Fault) -> Vec<u8> {
+        if input.len() != 16 {
+            panic!("Invalid block length");
+        }
+        if fault.position >= 16 {
+            panic!("Invalid fault position");
+        }
+
+        let num_rounds = self.num_rounds as usize;
+        let round_key = |round: usize| &self.key_schedule[round * 4..round * 4 + 4];
+
+        let mut state = aes::add_round_key(input, round_key(0));
+        for round in 1..num_rounds {
+            state = aes::sub_bytes(state);
+            state = aes::shift_rows(state);
+            if round == num_rounds - 1 {
+                state[fault.position] ^= fault.mask;
+            }
+            state = aes::mix_columns(state);
+            state = aes::add_round_key(state, round_key(round));
+        }
+        state = aes::sub_bytes(state);
+        state = aes::shift_rows(state);
+        aes::add_round_key(state, round_key(num_rounds))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DfaResult {
+    // candidates left for each column's four key bytes
+    pub candidates: [usize; 4],
+    pub round_key: Vec<u8>,
+    pub master_key: Vec<u8>,
+}
+
+// pairs are (correct, faulty) ciphertexts of the same plaintext, pairs that
+// don't look like a single byte fault are ignored. Returns None unless every
+// column of the last round key comes out unique.
+pub fn dfa_attack(pairs: &[(Vec<u8>, Vec<u8>)]) -> Option<DfaResult> {
+    let mut surviving: Vec<Option<HashSet<[u8; 4]>>> = vec![None; 4];
+
+    for (correct, faulty) in pairs {
+        let Some(column) = faulty_column(correct, faulty) else {
+            continue;
+        };
+        let found = column_candidates(correct, faulty, column);
+        surviving[column] = Some(match surviving[column].take() {
+            Some(previous) => previous.intersection(&found).copied().collect(),
+            None => found,
+        });
+    }
+
+    let mut candidates = [0; 4];
+    let mut round_key = vec![0; 16];
+    for (column, found) in surviving.iter().enumerate() {
+        let found = found.as_ref()?;
+        candidates[column] = found.len();
+        if found.len() != 1 {
+            return None;
+        }
+        let bytes = found.iter().next().unwrap();
+        for (row, byte) in bytes.iter().enumerate() {
+            round_key[output_position(column, row)] = *byte;
+        }
+    }
+
+    let master_key = key_schedule::invert_key_schedule(&round_key, 10, 128);
+    Some(DfaResult {
+        candidates,
+        round_key,
+        master_key,
+    })
+}
+
+// the ciphertext position that row of the faulty column ends up in after ShiftRows
+fn output_position(column: usize, row: usize) -> usize {
+    ((column + 4 - row) % 4) * 4 + row
+}
+
+// which column was hit, if the ciphertexts differ in exactly one column's four bytes
+fn faulty_column(correct: &[u8], faulty: &[u8]) -> Option<usize> {
+    let differing: Vec<usize> = (0..16).filter(|&position| correct[position] != faulty[position]).collect();
+    (0..4).find(|&column| {
+        let mut positions: Vec<usize> = (0..4).map(|row| output_position(column, row)).collect();
+        positions.sort();
+        positions == differing
+    })
+}
+
+// every guess for the column's four key bytes that explains one fault
+fn column_candidates(correct: &[u8], faulty: &[u8], column: usize) -> HashSet<[u8; 4]> {
+    // keys[row][difference], the key bytes giving that difference before the last SubBytes
+    let mut keys: Vec<Vec<Vec<u8>>> = vec![vec![Vec::new(); 256]; 4];
+    for (row, by_difference) in keys.iter_mut().enumerate() {
+        let position = output_position(column, row);
+        for guess in 0..=255u8 {
+            let difference = aes::inv_s_box(correct[position] ^ guess) ^ aes::inv_s_box(faulty[position] ^ guess);
+            by_difference[difference as usize].push(guess);
+        }
+    }
+
+    let mut found: HashSet<[u8; 4]> = HashSet::new();
+    for fault_row in 0..4 {
+        for error in 1..=255u8 {
+            let lists: Vec<&Vec<u8>> = (0..4)
+                .map(|row| &keys[row][aes::gf_mult(MIX[row][fault_row], error) as usize])
+                .collect();
+            if lists.iter().any(|list| list.is_empty()) {
+                continue;
+            }
+            for &k0 in lists[0] {
+                for &k1 in lists[1] {
+                    for &k2 in lists[2] {
+                        for &k3 in lists[3] {
+                            found.insert([k0, k1, k2, k3]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    found
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn injector_without_fault_is_aes() {
+        let key = crate::key_gen(128);
+        let injector = FaultInjector::new(key.clone());
+        let input = crate::iv_gen();
+
+        let expected = crate::crypto::aes_encrypt(input.clone(), key)[..16].to_vec();
+        assert_eq!(injector.encrypt(input.clone()), expected);
+        assert_eq!(injector.encrypt_with_fault(input, Fault { position: 3, mask: 0 }), expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid key length")]
+    fn injector_rejects_longer_keys() {
+        FaultInjector::new(crate::key_gen(192));
+    }
+
+    #[test]
+    fn fault_spreads_over_one_column() {
+        let injector = FaultInjector::new(crate::key_gen(128));
+        let input = crate::iv_gen();
+
+        // a fault in column 1 of the state
+        let correct = injector.encrypt(input.clone());
+        let faulty = injector.encrypt_with_fault(input, Fault { position: 6, mask: 0x5a });
+
+        assert_eq!(faulty_column(&correct, &faulty), Some(1));
+        assert_eq!((0..16).filter(|&i| correct[i] != faulty[i]).count(), 4);
+    }
+
+    #[test]
+    fn recovers_random_key() {
+        let key = crate::key_gen(128);
+        let injector = FaultInjector::new(key.clone());
+        let mut rng = rand::thread_rng();
+
+        let mut pairs: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        for column in 0..4 {
+            for _ in 0..3 {
+                let input = crate::iv_gen();
+                let fault = Fault {
+                    position: column * 4 + rng.gen_range(0..4),
+                    mask: rng.gen_range(1..=255),
+                };
+                pairs.push((injector.encrypt(input.clone()), injector.encrypt_with_fault(input, fault)));
+            }
+        }
+
+        let result = dfa_attack(&pairs).unwrap();
+        assert_eq!(result.master_key, key);
+        assert_eq!(result.round_key, aes::key_expansion(key)[40..44].concat());
+        assert_eq!(result.candidates, [1; 4]);
+    }
+
+    #[test]
+    fn one_fault_leaves_candidates() {
+        let injector = FaultInjector::new(crate::key_gen(128));
+        let input = crate::iv_gen();
+        let pair = (
+            injector.encrypt(input.clone()),
+            injector.encrypt_with_fault(input, Fault { position: 0, mask: 1 }),
+        );
+
+        assert!(column_candidates(&pair.0, &pair.1, 0).len() > 1);
+        // the other three columns have no faults at all
+        assert_eq!(dfa_attack(&[pair]), None);
+    }
+}
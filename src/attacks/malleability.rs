@@ -0,0 +1,212 @@
+use crate::crypto::{cbc, ccm, ctr, ocb, xor, Error};
+
+// Bit flipping against unauthenticated modes.
+//
+// CBC decrypts P_i = D(C_i) ^ C_{i-1}, so flipping a bit of C_{i-1} flips the
+// same bit of P_i, at the cost of turning P_{i-1} into garbage. CTR is a plain
+// XOR with the keystream so any plaintext bit can be flipped with no side
+// effects at all. The authenticated modes notice and refuse to decrypt.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteChange {
+    pub position: usize,
+    pub before: u8,
+    pub after: u8,
+}
+
+// what the receiver decrypted compared to what was sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub original: Vec<u8>,
+    pub tampered: Vec<u8>,
+    // positions in the transmitted bytes that were flipped, for CBC the iv comes first
+    pub flipped: Vec<usize>,
+    pub changes: Vec<ByteChange>,
+}
+
+impl Trace {
+    fn new(original: Vec<u8>, tampered: Vec<u8>, flipped: Vec<usize>) -> Trace {
+        let changes = original
+            .iter()
+            .zip(&tampered)
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(position, (&before, &after))| ByteChange { position, before, after })
+            .collect();
+
+        Trace {
+            original,
+            tampered,
+            flipped,
+            changes,
+        }
+    }
+
+    // 16 byte blocks of the plaintext that came out different
+    pub fn changed_blocks(&self) -> Vec<usize> {
+        let mut blocks: Vec<usize> = self.changes.iter().map(|change| change.position / 16).collect();
+        blocks.dedup();
+        blocks
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aead {
+    Ccm,
+    Ocb,
+}
+
+/* ----------- TAMPERING ------------ */
+// turns known plaintext at offset into wanted by editing the iv or the block before,
+// editing block i through C_{i-1} scrambles plaintext block i-1
+pub fn cbc_flip(
+    mut iv: Vec<u8>,
+    mut cipher_text: Vec<u8>,
+    offset: usize,
+    known: &[u8],
+    wanted: &[u8],
+) -> (Vec<u8>, Vec<u8>) {
+    if known.len() != wanted.len() || offset + known.len() > cipher_text.len() {
+        panic!("Invalid edit");
+    }
+
+    for (index, difference) in xor(known, wanted).into_iter().enumerate() {
+        let position = offset + index;
+        if position < 16 {
+            iv[position] ^= difference;
+        } else {
+            cipher_text[position - 16] ^= difference;
+        }
+    }
+    (iv, cipher_text)
+}
+
+// turns known plaintext at offset into wanted, nothing else changes
+pub fn ctr_flip(mut cipher_text: Vec<u8>, offset: usize, known: &[u8], wanted: &[u8]) -> Vec<u8> {
+    if known.len() != wanted.len() || offset + known.len() > cipher_text.len() {
+        panic!("Invalid edit");
+    }
+
+    for (index, difference) in xor(known, wanted).into_iter().enumerate() {
+        cipher_text[offset + index] ^= difference;
+    }
+    cipher_text
+}
+
+/* ----------- DEMONSTRATIONS ------------ */
+// encrypts message, rewrites the bytes at offset to wanted and decrypts again,
+// fails if the edit broke the padding
+pub fn cbc_demo(message: Vec<u8>, key: Vec<u8>, offset: usize, wanted: &[u8]) -> Result<Trace, Error> {
+    let iv = crate::iv_gen();
+    let cipher_text = cbc::aes_cbc_encrypt(message.clone(), key.clone(), iv.clone());
+
+    let known = &message[offset..offset + wanted.len()];
+    let (tampered_iv, tampered_cipher_text) = cbc_flip(iv.clone(), cipher_text.clone(), offset, known, wanted);
+
+    let sent: Vec<u8> = [iv, cipher_text].concat();
+    let received: Vec<u8> = [tampered_iv.clone(), tampered_cipher_text.clone()].concat();
+    let tampered = cbc::aes_cbc_decrypt(tampered_cipher_text, key, tampered_iv)?;
+
+    Ok(Trace::new(message, tampered, flipped_positions(&sent, &received)))
+}
+
+pub fn ctr_demo(message: Vec<u8>, key: Vec<u8>, offset: usize, wanted: &[u8]) -> Trace {
+    let counter = crate::iv_gen();
+    let cipher_text = ctr::aes_ctr_encrypt(message.clone(), key.clone(), counter.clone());
+
+    let known = &message[offset..offset + wanted.len()];
+    let tampered_cipher_text = ctr_flip(cipher_text.clone(), offset, known, wanted);
+
+    let flipped = flipped_positions(&cipher_text, &tampered_cipher_text);
+    let tampered = ctr::aes_ctr_decrypt(tampered_cipher_text, key, counter);
+
+    Trace::new(message, tampered, flipped)
+}
+
+// the same CTR style edit against an authenticated mode, the result is
+// whatever the receiver gets back from decryption
+pub fn aead_demo(aead: Aead, message: Vec<u8>, key: Vec<u8>, offset: usize, wanted: &[u8]) -> Result<Vec<u8>, Error> {
+    let known = message[offset..offset + wanted.len()].to_vec();
+
+    match aead {
+        Aead::Ccm => {
+            let nonce = crate::iv_gen()[..12].to_vec();
+            let cipher_text = ccm::aes_ccm_encrypt(message, key.clone(), nonce.clone(), Vec::new(), 16)?;
+            let tampered = ctr_flip(cipher_text, offset, &known, wanted);
+            ccm::aes_ccm_decrypt(tampered, key, nonce, Vec::new(), 16)
+        }
+        Aead::Ocb => {
+            let nonce = crate::iv_gen()[..12].to_vec();
+            let cipher_text = ocb::aes_ocb_encrypt(message, key.clone(), nonce.clone(), Vec::new(), 16);
+            let tampered = ctr_flip(cipher_text, offset, &known, wanted);
+            ocb::aes_ocb_decrypt(tampered, key, nonce, Vec::new(), 16)
+        }
+    }
+}
+
+fn flipped_positions(sent: &[u8], received: &[u8]) -> Vec<usize> {
+    sent.iter()
+        .zip(received)
+        .enumerate()
+        .filter(|(_, (sent, received))| sent != received)
+        .map(|(position, _)| position)
+        .collect()
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //                        |0             15|16            31|32            47|
+    const MESSAGE: &[u8; 48] = b"user=mallory;id=comment=hello!!!;admin=false;xxx";
+
+    #[test]
+    fn cbc_edits_one_block_and_scrambles_the_one_before() {
+        let trace = cbc_demo(MESSAGE.to_vec(), crate::key_gen(128), 39, b"true;").unwrap();
+
+        assert_eq!(trace.tampered[32..], *b";admin=true;;xxx");
+        assert_eq!(trace.tampered[..16], MESSAGE[..16]);
+        // flipping C_1 garbles all of P_1 as well, a single byte surviving is possible
+        assert_eq!(trace.changed_blocks(), vec![1, 2]);
+        assert!(trace.changes.iter().filter(|change| change.position / 16 == 1).count() > 8);
+
+        // ciphertext bytes 23 to 27 sit in C_1 and are sent after the 16 byte iv
+        assert_eq!(trace.flipped, vec![39, 40, 41, 42, 43]);
+    }
+
+    #[test]
+    fn cbc_first_block_through_the_iv() {
+        let trace = cbc_demo(MESSAGE.to_vec(), crate::key_gen(128), 5, b"admin!!").unwrap();
+
+        assert_eq!(trace.tampered[..16], *b"user=admin!!;id=");
+        assert_eq!(trace.tampered[16..], MESSAGE[16..]);
+        assert_eq!(trace.flipped, vec![5, 6, 7, 8, 9, 10, 11]);
+    }
+
+    #[test]
+    fn ctr_edits_exactly() {
+        let trace = ctr_demo(MESSAGE.to_vec(), crate::key_gen(256), 39, b"true;");
+
+        assert_eq!(trace.tampered, b"user=mallory;id=comment=hello!!!;admin=true;;xxx".to_vec());
+        assert_eq!(trace.flipped, vec![39, 40, 41, 42, 43]);
+        assert_eq!(
+            trace.changes.iter().map(|change| change.position).collect::<Vec<_>>(),
+            vec![39, 40, 41, 42, 43]
+        );
+    }
+
+    #[test]
+    fn authenticated_modes_reject_edits() {
+        for aead in [Aead::Ccm, Aead::Ocb] {
+            let result = aead_demo(aead, MESSAGE.to_vec(), crate::key_gen(128), 39, b"true;");
+            assert_eq!(result, Err(Error::AuthenticationFailed));
+
+            // an edit that changes nothing still decrypts
+            let result = aead_demo(aead, MESSAGE.to_vec(), crate::key_gen(128), 39, b"false");
+            assert_eq!(result, Ok(MESSAGE.to_vec()));
+        }
+    }
+}
@@ -0,0 +1,514 @@
+use rand::Rng;
+
+use crate::crypto::gcm::{from_block, gf_mul, ghash, pad_partial, to_block};
+use crate::crypto::xor;
+
+// Nonce reuse in CTR and GCM.
+//
+// The same key and nonce give the same keystream, so XORing two ciphertexts
+// cancels it and leaves P_1 ^ P_2. Sliding a guessed word (a crib) along that
+// and looking for readable text on the other side recovers both messages.
+//
+// GCM tags are T = GHASH_H(A, C) ^ E(K, J_0). With a repeated nonce the mask
+// E(K, J_0) is the same for both messages, so T_1 ^ T_2 is a known polynomial
+// in the hash key H. Its roots are the candidates for H, and with H and one
+// tag anyone can forge tags for new messages under that nonce.
+
+/* ----------- KEYSTREAM REUSE ------------ */
+// P_1 ^ P_2, as long as the shorter ciphertext
+pub fn xor_cipher_texts(first: &[u8], second: &[u8]) -> Vec<u8> {
+    xor(first, second)
+}
+
+// one known plaintext gives the keystream, which decrypts every other
+// message under the same key and nonce up to its length
+pub fn recover_keystream(plain_text: &[u8], cipher_text: &[u8]) -> Vec<u8> {
+    xor(plain_text, cipher_text)
+}
+
+// relative frequencies of English letters and the space, in percent
+const LETTER_FREQUENCIES: [f64; 26] = [
+    8.2, 1.5, 2.8, 4.3, 12.7, 2.2, 2.0, 6.1, 7.0, 0.15, 0.77, 4.0, 2.4, 6.7, 7.5, 1.9, 0.095, 6.0, 6.3, 9.1, 2.8, 0.98,
+    2.4, 0.15, 2.0, 0.074,
+];
+const SPACE_FREQUENCY: f64 = 19.0;
+
+// average log likelihood of the bytes under a simple English model,
+// higher is more English, anything unprintable is heavily penalised
+pub fn english_score(text: &[u8]) -> f64 {
+    if text.is_empty() {
+        return 0.0;
+    }
+
+    let total: f64 = text
+        .iter()
+        .map(|&byte| match byte {
+            b'a'..=b'z' => LETTER_FREQUENCIES[(byte - b'a') as usize].log10(),
+            // capitals are mostly at the start of sentences
+            b'A'..=b'Z' => LETTER_FREQUENCIES[(byte - b'A') as usize].log10() - 1.0,
+            b' ' => SPACE_FREQUENCY.log10(),
+            b'.' | b',' | b'\'' => 0.0,
+            b'0'..=b'9' | b'!' | b'?' | b'-' | b':' | b';' | b'"' => -1.0,
+            0x21..=0x7e => -2.0,
+            _ => -6.0,
+        })
+        .sum();
+
+    total / text.len() as f64
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CribMatch {
+    pub offset: usize,
+    // what the other message would say at this offset if the crib is right
+    pub revealed: Vec<u8>,
+    pub score: f64,
+}
+
+// tries the crib at every offset, best scoring first
+pub fn crib_drag(xored: &[u8], crib: &[u8]) -> Vec<CribMatch> {
+    if crib.is_empty() || crib.len() > xored.len() {
+        return Vec::new();
+    }
+
+    let mut matches: Vec<CribMatch> = (0..=xored.len() - crib.len())
+        .map(|offset| {
+            let revealed = xor(&xored[offset..offset + crib.len()], crib);
+            let score = english_score(&revealed);
+            CribMatch { offset, revealed, score }
+        })
+        .collect();
+
+    matches.sort_by(|left, right| right.score.total_cmp(&left.score));
+    matches
+}
+
+// the bytes of both messages known so far, filled in one crib at a time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CribDragger {
+    xored: Vec<u8>,
+    first: Vec<Option<u8>>,
+    second: Vec<Option<u8>>,
+}
+
+impl CribDragger {
+    pub fn new(xored: Vec<u8>) -> CribDragger {
+        let length = xored.len();
+        CribDragger {
+            xored,
+            first: vec![None; length],
+            second: vec![None; length],
+        }
+    }
+
+    // the crib is taken to be in the first message, or the second if in_second
+    pub fn place(&mut self, offset: usize, crib: &[u8], in_second: bool) {
+        if offset + crib.len() > self.xored.len() {
+            panic!("Crib runs past the end");
+        }
+
+        for (index, &byte) in crib.iter().enumerate() {
+            let position = offset + index;
+            let other = byte ^ self.xored[position];
+            let (mine, theirs) = if in_second { (other, byte) } else { (byte, other) };
+            self.first[position] = Some(mine);
+            self.second[position] = Some(theirs);
+        }
+    }
+
+    // places each crib at its best offset that agrees with what is already
+    // known, skipping any that don't read as English somewhere
+    pub fn drag_all(&mut self, cribs: &[&[u8]], threshold: f64) {
+        for crib in cribs {
+            for candidate in crib_drag(&self.xored, crib) {
+                if candidate.score < threshold {
+                    break;
+                }
+                if self.agrees(candidate.offset, crib, false) {
+                    self.place(candidate.offset, crib, false);
+                    break;
+                }
+                if self.agrees(candidate.offset, crib, true) {
+                    self.place(candidate.offset, crib, true);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn agrees(&self, offset: usize, crib: &[u8], in_second: bool) -> bool {
+        let known = if in_second { &self.second } else { &self.first };
+        crib.iter()
+            .zip(&known[offset..])
+            .all(|(&byte, known)| known.is_none_or(|known| known == byte))
+    }
+
+    pub fn first(&self) -> &[Option<u8>] {
+        &self.first
+    }
+
+    pub fn second(&self) -> &[Option<u8>] {
+        &self.second
+    }
+
+    // both messages with unknown bytes shown as '_'
+    pub fn render(&self) -> (String, String) {
+        let render = |bytes: &[Option<u8>]| {
+            bytes
+                .iter()
+                .map(|byte| match byte {
+                    Some(byte) if byte.is_ascii_graphic() || *byte == b' ' => *byte as char,
+                    Some(_) => '?',
+                    None => '_',
+                })
+                .collect()
+        };
+        (render(&self.first), render(&self.second))
+    }
+}
+
+/* ----------- GCM HASH KEY RECOVERY ------------ */
+// a GCM message as it goes over the wire, the tag must be the full 16 bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GcmMessage {
+    pub associated_data: Vec<u8>,
+    pub cipher_text: Vec<u8>,
+    pub tag: Vec<u8>,
+}
+
+impl GcmMessage {
+    // splits the output of aes_gcm_encrypt
+    pub fn from_sealed(associated_data: Vec<u8>, mut sealed: Vec<u8>) -> GcmMessage {
+        if sealed.len() < 16 {
+            panic!("Invalid input length");
+        }
+        let tag = sealed.split_off(sealed.len() - 16);
+        GcmMessage {
+            associated_data,
+            cipher_text: sealed,
+            tag,
+        }
+    }
+
+    pub fn sealed(&self) -> Vec<u8> {
+        [self.cipher_text.clone(), self.tag.clone()].concat()
+    }
+}
+
+// every H that explains both tags, the real one is always among them
+pub fn recover_hash_key(first: &GcmMessage, second: &GcmMessage) -> Vec<u128> {
+    let mut polynomial = add(
+        &ghash_polynomial(&first.associated_data, &first.cipher_text),
+        &ghash_polynomial(&second.associated_data, &second.cipher_text),
+    );
+    let tags = from_block(&first.tag) ^ from_block(&second.tag);
+    if polynomial.is_empty() {
+        polynomial.push(tags);
+    } else {
+        polynomial[0] ^= tags;
+    }
+
+    let mut roots = find_roots(trim(polynomial));
+    roots.sort();
+    roots
+}
+
+// narrows candidates down with another message under the same nonce
+pub fn filter_hash_keys(candidates: &[u128], known: &GcmMessage, other: &GcmMessage) -> Vec<u128> {
+    candidates
+        .iter()
+        .copied()
+        .filter(|&h| mask(h, known) == mask(h, other))
+        .collect()
+}
+
+// a valid ciphertext and tag for new contents under the reused nonce
+pub fn forge(h: u128, known: &GcmMessage, associated_data: Vec<u8>, cipher_text: Vec<u8>) -> GcmMessage {
+    let tag = to_block(ghash(h, &associated_data, &cipher_text) ^ mask(h, known));
+    GcmMessage {
+        associated_data,
+        cipher_text,
+        tag,
+    }
+}
+
+// E(K, J_0), the same for every message under one nonce
+fn mask(h: u128, message: &GcmMessage) -> u128 {
+    from_block(&message.tag) ^ ghash(h, &message.associated_data, &message.cipher_text)
+}
+
+// GHASH as a polynomial in H, lowest degree first: X_1 H^m + ... + X_m H
+fn ghash_polynomial(associated_data: &[u8], cipher_text: &[u8]) -> Vec<u128> {
+    let mut blocks: Vec<u128> = associated_data
+        .chunks(16)
+        .chain(cipher_text.chunks(16))
+        .map(pad_partial)
+        .collect();
+    blocks.push(((associated_data.len() as u128 * 8) << 64) | (cipher_text.len() as u128 * 8));
+
+    let mut polynomial = vec![0];
+    polynomial.extend(blocks.into_iter().rev());
+    polynomial
+}
+
+/* ----------- POLYNOMIALS OVER GF(2^128) ------------ */
+// the GCM bit order puts 1 in the top bit
+const ONE: u128 = 1 << 127;
+
+fn gf_inverse(value: u128) -> u128 {
+    if value == 0 {
+        panic!("Zero has no inverse");
+    }
+    // value^(2^128 - 2) = value^2 * value^4 * ... * value^(2^127)
+    let mut result = ONE;
+    let mut square = value;
+    for _ in 1..128 {
+        square = gf_mul(square, square);
+        result = gf_mul(result, square);
+    }
+    result
+}
+
+fn trim(mut polynomial: Vec<u128>) -> Vec<u128> {
+    while polynomial.last() == Some(&0) {
+        polynomial.pop();
+    }
+    polynomial
+}
+
+fn degree(polynomial: &[u128]) -> usize {
+    polynomial.len().saturating_sub(1)
+}
+
+fn add(left: &[u128], right: &[u128]) -> Vec<u128> {
+    let mut sum = vec![0; left.len().max(right.len())];
+    for (index, coefficient) in left.iter().enumerate() {
+        sum[index] ^= coefficient;
+    }
+    for (index, coefficient) in right.iter().enumerate() {
+        sum[index] ^= coefficient;
+    }
+    trim(sum)
+}
+
+// quotient and remainder
+fn divide(dividend: &[u128], divisor: &[u128]) -> (Vec<u128>, Vec<u128>) {
+    let inverse = gf_inverse(*divisor.last().expect("Division by zero polynomial"));
+
+    let mut remainder = trim(dividend.to_vec());
+    let mut quotient = vec![0; remainder.len().saturating_sub(divisor.len()) + 1];
+
+    while !remainder.is_empty() && remainder.len() >= divisor.len() {
+        let shift = remainder.len() - divisor.len();
+        let factor = gf_mul(*remainder.last().unwrap(), inverse);
+        quotient[shift] = factor;
+        for (index, &coefficient) in divisor.iter().enumerate() {
+            remainder[shift + index] ^= gf_mul(factor, coefficient);
+        }
+        remainder = trim(remainder);
+    }
+
+    (trim(quotient), remainder)
+}
+
+fn gcd(left: &[u128], right: &[u128]) -> Vec<u128> {
+    let (mut a, mut b) = (trim(left.to_vec()), trim(right.to_vec()));
+    while !b.is_empty() {
+        let (_, remainder) = divide(&a, &b);
+        a = b;
+        b = remainder;
+    }
+    monic(a)
+}
+
+fn monic(polynomial: Vec<u128>) -> Vec<u128> {
+    match polynomial.last() {
+        Some(&leading) => {
+            let inverse = gf_inverse(leading);
+            polynomial.iter().map(|&coefficient| gf_mul(coefficient, inverse)).collect()
+        }
+        None => polynomial,
+    }
+}
+
+// squaring is linear in characteristic 2, the coefficients just spread out
+fn square_mod(polynomial: &[u128], modulus: &[u128]) -> Vec<u128> {
+    let mut square = vec![0; polynomial.len() * 2];
+    for (index, &coefficient) in polynomial.iter().enumerate() {
+        square[index * 2] = gf_mul(coefficient, coefficient);
+    }
+    divide(&square, modulus).1
+}
+
+// roots of a polynomial over GF(2^128), without repeats
+fn find_roots(polynomial: Vec<u128>) -> Vec<u128> {
+    if degree(&polynomial) < 1 {
+        return Vec::new();
+    }
+    let polynomial = monic(polynomial);
+
+    // every element of the field is a root of X^(2^128) - X, so the gcd with
+    // it keeps exactly the linear factors
+    let mut power = vec![0, ONE];
+    for _ in 0..128 {
+        power = square_mod(&power, &polynomial);
+    }
+    let linear = gcd(&polynomial, &add(&power, &[0, ONE]));
+
+    let mut roots: Vec<u128> = Vec::new();
+    split(linear, &mut roots);
+    roots
+}
+
+// equal degree splitting, the trace of aX is 0 for about half of the roots
+fn split(polynomial: Vec<u128>, roots: &mut Vec<u128>) {
+    match degree(&polynomial) {
+        0 => {}
+        // X + c
+        1 => roots.push(polynomial[0]),
+        _ => {
+            let mut rng = rand::thread_rng();
+            loop {
+                let start = vec![0, rng.gen::<u128>()];
+                let mut term = divide(&start, &polynomial).1;
+                let mut trace = term.clone();
+                for _ in 1..128 {
+                    term = square_mod(&term, &polynomial);
+                    trace = add(&trace, &term);
+                }
+
+                let factor = gcd(&polynomial, &trace);
+                if degree(&factor) > 0 && degree(&factor) < degree(&polynomial) {
+                    let (rest, _) = divide(&polynomial, &factor);
+                    split(factor, roots);
+                    split(monic(rest), roots);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{ctr, gcm};
+
+    const FIRST: &[u8] = b"Meet me at the old bridge after the meeting and bring the documents";
+    const SECOND: &[u8] = b"The weather is lovely today so the picnic is on by the river bank";
+
+    #[test]
+    fn keystream_reuse_cancels_out() {
+        let key = crate::key_gen(128);
+        let counter = crate::iv_gen();
+        let first = ctr::aes_ctr_encrypt(FIRST.to_vec(), key.clone(), counter.clone());
+        let second = ctr::aes_ctr_encrypt(SECOND.to_vec(), key, counter);
+
+        assert_eq!(xor_cipher_texts(&first, &second), xor(FIRST, SECOND));
+
+        let keystream = recover_keystream(FIRST, &first);
+        assert_eq!(xor(&keystream, &second), SECOND.to_vec());
+    }
+
+    #[test]
+    fn english_scores_higher() {
+        assert!(english_score(b"the river bank") > english_score(b"x#q\x01zz\x7f"));
+        assert!(english_score(b"hello there") > english_score(b"HQZXJ KVQ"));
+    }
+
+    #[test]
+    fn crib_dragging_reveals_the_other_message() {
+        let xored = xor(FIRST, SECOND);
+
+        // " the " sits at 10 in the first message, the second reads "r is " there
+        let best = &crib_drag(&xored, b" the ")[..5];
+        let correct = best.iter().find(|candidate| candidate.offset == 10).unwrap();
+        assert_eq!(correct.revealed, SECOND[10..15].to_vec());
+
+        let mut dragger = CribDragger::new(xored);
+        dragger.place(0, b"Meet me at the ", false);
+        let (first, second) = dragger.render();
+        assert!(first.starts_with("Meet me at the _"));
+        assert!(second.starts_with("The weather is _"));
+        assert_eq!(dragger.second()[4], Some(b'w'));
+    }
+
+    #[test]
+    fn drag_all_fills_in_consistent_cribs() {
+        let mut dragger = CribDragger::new(xor(FIRST, SECOND));
+        dragger.drag_all(&[b"Meet me at the ", b" the weather "], -1.5);
+
+        // whichever side each crib landed on, every placed byte has to be right
+        for (index, (first, second)) in dragger.first().iter().zip(dragger.second()).enumerate() {
+            if let (Some(first), Some(second)) = (first, second) {
+                assert_eq!(first ^ second, FIRST[index] ^ SECOND[index]);
+            }
+        }
+        assert!(dragger.first().iter().filter(|byte| byte.is_some()).count() >= 15);
+    }
+
+    fn multiply(left: &[u128], right: &[u128]) -> Vec<u128> {
+        if left.is_empty() || right.is_empty() {
+            return Vec::new();
+        }
+        let mut product = vec![0; left.len() + right.len() - 1];
+        for (i, &a) in left.iter().enumerate() {
+            for (j, &b) in right.iter().enumerate() {
+                product[i + j] ^= gf_mul(a, b);
+            }
+        }
+        trim(product)
+    }
+
+    #[test]
+    fn polynomial_roots() {
+        // (X + a)(X + b)(X + c)(X + a), the repeated root only comes out once
+        let (a, b, c) = (0x1234 << 100, ONE, 0xdead_beef);
+        let mut polynomial = vec![ONE];
+        for root in [a, b, c, a] {
+            polynomial = multiply(&polynomial, &[root, ONE]);
+        }
+
+        let mut roots = find_roots(polynomial);
+        roots.sort();
+        let mut expected = vec![a, b, c];
+        expected.sort();
+        assert_eq!(roots, expected);
+
+        assert_eq!(gf_mul(gf_inverse(a), a), ONE);
+    }
+
+    #[test]
+    fn recovers_gcm_hash_key_and_forges() {
+        let key = crate::key_gen(128);
+        let nonce = crate::iv_gen()[..12].to_vec();
+        let seal = |plain_text: &[u8], associated_data: &[u8]| {
+            GcmMessage::from_sealed(
+                associated_data.to_vec(),
+                gcm::aes_gcm_encrypt(plain_text.to_vec(), key.clone(), nonce.clone(), associated_data.to_vec(), 16),
+            )
+        };
+
+        let first = seal(FIRST, b"header one");
+        let second = seal(SECOND, b"header two");
+        let third = seal(b"a third message", b"");
+
+        let h = from_block(&crate::crypto::aes_encrypt(vec![0; 16], key.clone())[..16]);
+        let candidates = recover_hash_key(&first, &second);
+        assert!(candidates.contains(&h));
+
+        let candidates = filter_hash_keys(&candidates, &first, &third);
+        assert_eq!(candidates, vec![h]);
+
+        // flip a ciphertext bit and give it a matching tag
+        let mut cipher_text = first.cipher_text.clone();
+        cipher_text[0] ^= 0x20;
+        let forged = forge(h, &first, b"forged".to_vec(), cipher_text);
+
+        let opened = gcm::aes_gcm_decrypt(forged.sealed(), key, nonce, forged.associated_data, 16).unwrap();
+        assert_eq!(opened[..4], *b"meet");
+    }
+}
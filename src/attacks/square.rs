@@ -0,0 +1,116 @@
+use rand::Rng;
+
+use crate::crypto::{aes, key_schedule};
+
+// Square (integral) attack on 4-round AES-128 with no MixColumns in the last round.
+//
+// A Λ-set is 256 plaintexts where one byte takes every value and the rest are
+// constant. After three rounds every byte of the state XORs to zero over the
+// set, so peeling off the last round with the right key byte has to give a
+// zero sum. Wrong guesses only survive by chance, a few sets pin each byte down.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SquareResult {
+    // number of surviving guesses for each byte after every Λ-set
+    pub candidates: Vec<[usize; 16]>,
+    pub round_key: Vec<u8>,
+    pub master_key: Vec<u8>,
+}
+
+// oracle encrypts a chosen plaintext under the unknown key,
+// max_sets bounds how many Λ-sets are asked for before giving up
+pub fn square_attack(oracle: &mut dyn FnMut(Vec<u8>) -> Vec<u8>, max_sets: usize) -> Option<SquareResult> {
+    let mut rng = rand::thread_rng();
+
+    let mut surviving: Vec<Vec<u8>> = vec![(0..=255).collect(); 16];
+    let mut candidates: Vec<[usize; 16]> = Vec::new();
+
+    for _ in 0..max_sets {
+        let constant: Vec<u8> = (0..16).map(|_| rng.gen()).collect();
+        let cipher_texts: Vec<Vec<u8>> = lambda_set(&constant, 0).into_iter().map(&mut *oracle).collect();
+
+        for (position, guesses) in surviving.iter_mut().enumerate() {
+            guesses.retain(|&guess| is_balanced(&cipher_texts, position, guess));
+        }
+
+        let mut counts = [0; 16];
+        for (count, guesses) in counts.iter_mut().zip(&surviving) {
+            *count = guesses.len();
+        }
+        candidates.push(counts);
+
+        if surviving.iter().any(|guesses| guesses.is_empty()) {
+            return None;
+        }
+        if surviving.iter().all(|guesses| guesses.len() == 1) {
+            let round_key: Vec<u8> = surviving.iter().map(|guesses| guesses[0]).collect();
+            let master_key = key_schedule::invert_key_schedule(&round_key, 4, 128);
+
+            return Some(SquareResult {
+                candidates,
+                round_key,
+                master_key,
+            });
+        }
+    }
+
+    None
+}
+
+// 256 plaintexts with byte active taking every value
+pub fn lambda_set(constant: &[u8], active: usize) -> Vec<Vec<u8>> {
+    (0..=255)
+        .map(|value| {
+            let mut plain_text = constant.to_vec();
+            plain_text[active] = value;
+            plain_text
+        })
+        .collect()
+}
+
+// undo the last AddRoundKey and SubBytes for one byte and check the sum,
+// ShiftRows only moves the byte so it doesn't matter for a single position
+fn is_balanced(cipher_texts: &[Vec<u8>], position: usize, guess: u8) -> bool {
+    cipher_texts
+        .iter()
+        .fold(0, |sum, cipher_text| sum ^ aes::inv_s_box(cipher_text[position] ^ guess))
+        == 0
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::reduced::ReducedAes;
+
+    #[test]
+    fn three_rounds_are_balanced() {
+        let reduced = ReducedAes::new(crate::key_gen(128), 3, true);
+        let sum = lambda_set(&[0x11; 16], 5)
+            .into_iter()
+            .map(|plain_text| reduced.encrypt_block(plain_text))
+            .fold(vec![0; 16], |sum, cipher_text| crate::crypto::xor(&sum, &cipher_text));
+
+        assert_eq!(sum, vec![0; 16]);
+    }
+
+    #[test]
+    fn recovers_random_key() {
+        let key = crate::key_gen(128);
+        let reduced = ReducedAes::new(key.clone(), 4, false);
+
+        let mut queries = 0;
+        let mut oracle = |plain_text: Vec<u8>| {
+            queries += 1;
+            reduced.encrypt_block(plain_text)
+        };
+
+        let result = square_attack(&mut oracle, 8).unwrap();
+
+        assert_eq!(result.round_key, reduced.round_key(4));
+        assert_eq!(result.master_key, key);
+        assert_eq!(queries, 256 * result.candidates.len());
+        assert!(result.candidates[0].iter().all(|&count| count >= 1));
+    }
+}
@@ -0,0 +1,188 @@
+use std::hint::black_box;
+use std::time::Instant;
+
+use rand::Rng;
+
+use crate::crypto::block::BlockCipher;
+
+// A dudect style constant time test (Reparaz, Balasch and Verbauwhede).
+//
+// The target is timed on two classes of input, one fixed block and fresh
+// random blocks, interleaved in random order. If the running time doesn't
+// depend on the data the two timing distributions match, Welch's t-test
+// checks whether their means differ. |t| above about 4.5 is a leak.
+
+pub const THRESHOLD: f64 = 4.5;
+
+// running mean and variance of both classes, Welford's method
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WelchT {
+    count: [f64; 2],
+    mean: [f64; 2],
+    m2: [f64; 2],
+}
+
+impl WelchT {
+    pub fn new() -> WelchT {
+        WelchT::default()
+    }
+
+    pub fn push(&mut self, class: usize, value: f64) {
+        self.count[class] += 1.0;
+        let delta = value - self.mean[class];
+        self.mean[class] += delta / self.count[class];
+        self.m2[class] += delta * (value - self.mean[class]);
+    }
+
+    pub fn mean(&self, class: usize) -> f64 {
+        self.mean[class]
+    }
+
+    pub fn variance(&self, class: usize) -> f64 {
+        if self.count[class] < 2.0 {
+            return 0.0;
+        }
+        self.m2[class] / (self.count[class] - 1.0)
+    }
+
+    pub fn t(&self) -> f64 {
+        if self.count[0] < 2.0 || self.count[1] < 2.0 {
+            return 0.0;
+        }
+        let spread = self.variance(0) / self.count[0] + self.variance(1) / self.count[1];
+        if spread == 0.0 {
+            return 0.0;
+        }
+        (self.mean[0] - self.mean[1]) / spread.sqrt()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingReport {
+    // measurements taken for the fixed and the random class
+    pub samples: [usize; 2],
+    // mean time in nanoseconds for each class
+    pub means: [f64; 2],
+    // t over every measurement, and over those below the 90th percentile
+    // which throws away most interrupts and cache misses from elsewhere
+    pub t: f64,
+    pub cropped_t: f64,
+}
+
+impl TimingReport {
+    pub fn max_t(&self) -> f64 {
+        self.t.abs().max(self.cropped_t.abs())
+    }
+
+    pub fn leaks(&self) -> bool {
+        self.max_t() > THRESHOLD
+    }
+}
+
+// times target on fixed and on random inputs of the same length
+pub fn measure(target: &mut dyn FnMut(&[u8]), fixed: &[u8], samples: usize) -> TimingReport {
+    let mut rng = rand::thread_rng();
+
+    // inputs are prepared up front so only the target is timed
+    let classes: Vec<usize> = (0..samples).map(|_| rng.gen_range(0..2)).collect();
+    let inputs: Vec<Vec<u8>> = classes
+        .iter()
+        .map(|&class| match class {
+            0 => fixed.to_vec(),
+            _ => (0..fixed.len()).map(|_| rng.gen()).collect(),
+        })
+        .collect();
+
+    let mut times: Vec<f64> = Vec::with_capacity(samples);
+    for input in &inputs {
+        let start = Instant::now();
+        target(black_box(input));
+        times.push(start.elapsed().as_nanos() as f64);
+    }
+
+    let mut sorted = times.clone();
+    sorted.sort_by(f64::total_cmp);
+    let cutoff = sorted.get(samples * 9 / 10).copied().unwrap_or(f64::INFINITY);
+
+    let mut all = WelchT::new();
+    let mut cropped = WelchT::new();
+    for (&class, &time) in classes.iter().zip(&times) {
+        all.push(class, time);
+        if time <= cutoff {
+            cropped.push(class, time);
+        }
+    }
+
+    TimingReport {
+        samples: [
+            classes.iter().filter(|&&class| class == 0).count(),
+            classes.iter().filter(|&&class| class == 1).count(),
+        ],
+        means: [all.mean(0), all.mean(1)],
+        t: all.t(),
+        cropped_t: cropped.t(),
+    }
+}
+
+// runs the test against a block cipher's encryption with the all zero block as the fixed class
+pub fn test_block_cipher(cipher: &dyn BlockCipher, samples: usize) -> TimingReport {
+    let fixed = vec![0; cipher.block_size()];
+    let mut target = |input: &[u8]| {
+        black_box(cipher.encrypt_block(input.to_vec()));
+    };
+    measure(&mut target, &fixed, samples)
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::block::Aes;
+
+    #[test]
+    fn welch_t_statistic() {
+        let mut welch = WelchT::new();
+        for value in [1.0, 2.0, 3.0, 4.0] {
+            welch.push(0, value);
+        }
+        for value in [3.0, 4.0, 5.0, 6.0] {
+            welch.push(1, value);
+        }
+
+        assert_eq!(welch.mean(0), 2.5);
+        assert!((welch.variance(0) - 5.0 / 3.0).abs() < 1e-12);
+        // (2.5 - 4.5) / sqrt(5/12 + 5/12)
+        assert!((welch.t() - -2.0 / (5.0f64 / 6.0).sqrt()).abs() < 1e-12);
+
+        assert_eq!(WelchT::new().t(), 0.0);
+    }
+
+    #[test]
+    fn detects_an_obvious_leak() {
+        // does far more work whenever the first byte is zero, as the fixed input's is
+        let mut leaky = |input: &[u8]| {
+            let rounds = if input[0] == 0 { 20_000 } else { 10 };
+            let mut sum: u64 = 0;
+            for i in 0..rounds {
+                sum = black_box(sum.wrapping_add(i));
+            }
+            black_box(sum);
+        };
+
+        let report = measure(&mut leaky, &[0; 16], 2_000);
+        assert!(report.leaks());
+        assert!(report.means[0] > report.means[1]);
+        assert_eq!(report.samples[0] + report.samples[1], 2_000);
+    }
+
+    #[test]
+    fn runs_against_aes() {
+        let report = test_block_cipher(&Aes::new(crate::key_gen(128)), 1_000);
+
+        assert_eq!(report.samples[0] + report.samples[1], 1_000);
+        assert!(report.t.is_finite() && report.cropped_t.is_finite());
+        assert!(report.means.iter().all(|&mean| mean > 0.0));
+    }
+}
@@ -1,90 +1,102 @@
-mod aes;
-
-pub fn aes_encrypt(input: Vec<u8>, key: Vec<u8>) -> Vec<u8> {
-    let num_rounds: u8 = match key.len() / 4 {
-        4 => 10,
-        6 => 12,
-        8 => 14,
-        _ => panic!("Invalid key length"),
-   };
-
-    let key_schedule = aes::key_expansion(key);
-
-    //ECB
-    // take the blocks
-    let mut cipher_text: Vec<u8> = Vec::new();
-
-    // Encrypt the even sized blocks
-    let num_blocks = input.len() / 16;
-    for i in 0..num_blocks {
-        let mut block: Vec<u8> = Vec::new();
-        block.extend_from_slice(&input[i * 16..(i + 1) * 16]);
+pub(crate) mod aes;
+pub mod block;
+pub mod cavp;
+pub mod cbc;
+pub mod ccm;
+pub mod cfb;
+pub mod ctr;
+pub mod custom;
+pub mod gcm;
+pub mod gf256;
+pub mod key_schedule;
+pub mod keywrap;
+pub mod metrics;
+pub mod nonce;
+pub mod ocb;
+pub mod ofb;
+pub mod padding;
+pub mod reduced;
+pub mod rijndael;
+pub mod sbox;
+pub mod xts;
+
+use std::fmt;
+
+use block::{Aes, BlockMode, Ecb};
+use padding::{Padding, Pkcs7};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    InvalidLength,
+    IntegrityCheckFailed,
+    AuthenticationFailed,
+    InvalidPadding,
+    NonceReused,
+    InvalidHex,
+    InvalidKey,
+    InvalidUtf8,
+}
 
-        let mut encrypted = aes::cipher(block, num_rounds, &key_schedule);
-        cipher_text.append(&mut encrypted);
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidLength => write!(f, "Invalid input length"),
+            Error::IntegrityCheckFailed => write!(f, "Integrity check failed"),
+            Error::AuthenticationFailed => write!(f, "Authentication failed"),
+            Error::InvalidPadding => write!(f, "Invalid padding"),
+            Error::NonceReused => write!(f, "Nonce already used with this key"),
+            Error::InvalidHex => write!(f, "Invalid hex"),
+            Error::InvalidKey => write!(f, "Key must be 128, 192 or 256 bits"),
+            Error::InvalidUtf8 => write!(f, "Decrypted text is not valid UTF-8"),
+        }
     }
+}
 
-    // Encrypt the remainder
-    let remainder = input.len() % 16;
-    let padding = 16 - remainder;
-
-    // if there is no remainder, add a full block of padding
-    if remainder == 0 {
-        let block = vec![padding as u8; 16];
-        let mut encrypted = aes::cipher(block, num_rounds, &key_schedule);
-        cipher_text.append(&mut encrypted);
-    }
-    // otherwise, add whatever padding is left
-    else {
-        let mut block: Vec<u8> = Vec::new();
-        block.extend_from_slice(&input[num_blocks * 16..num_blocks * 16 + remainder]);
-        block.resize(16, padding as u8);
-
-        let mut encrypted = aes::cipher(block, num_rounds, &key_schedule);
-        cipher_text.append(&mut encrypted);
-    }
+impl std::error::Error for Error {}
 
-    cipher_text
+pub(crate) fn xor(left: &[u8], right: &[u8]) -> Vec<u8> {
+    left.iter().zip(right).map(|(l, r)| l ^ r).collect()
 }
 
-pub fn aes_decrypt(input: Vec<u8>, key: Vec<u8>) -> Vec<u8> {
-    let num_rounds: u8 = match key.len() / 4 {
-        4 => 10,
-        6 => 12,
-        8 => 14,
-        _ => panic!("Invalid key length"),
-    };
+// compare tags without exiting early on the first difference
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len() && left.iter().zip(right).fold(0, |acc, (l, r)| acc | (l ^ r)) == 0
+}
 
-    if input.len() % 16 != 0 {
-        panic!("Invalid ciphertext length");
-    }
-    
-    let key_schedule = aes::key_expansion(key);
+pub fn aes_encrypt(input: Vec<u8>, key: Vec<u8>) -> Vec<u8> {
+    aes_encrypt_with_padding(input, key, &Pkcs7)
+}
 
-    let mut plain_text: Vec<u8> = Vec::new();
+pub fn aes_decrypt(input: Vec<u8>, key: Vec<u8>) -> Result<Vec<u8>, Error> {
+    aes_decrypt_with_padding(input, key, &Pkcs7)
+}
 
-    let num_blocks = input.len() / 16;
-    for i in 0..num_blocks {
-        let mut block: Vec<u8> = Vec::new();
-        block.extend_from_slice(&input[i * 16..(i + 1) * 16]);
+pub fn aes_encrypt_with_padding(input: Vec<u8>, key: Vec<u8>, padding: &dyn Padding) -> Vec<u8> {
+    //ECB
+    let mut mode = Ecb::new(Aes::new(key));
+    encrypt_with_mode(input, &mut mode, padding)
+}
 
-        let mut decrypted = aes::inv_cipher(block, num_rounds, &key_schedule);
-        plain_text.append(&mut decrypted);
-    }
+pub fn aes_decrypt_with_padding(input: Vec<u8>, key: Vec<u8>, padding: &dyn Padding) -> Result<Vec<u8>, Error> {
+    let mut mode = Ecb::new(Aes::new(key));
+    decrypt_with_mode(input, &mut mode, padding)
+}
 
-    // find the padding
-    let padding_length = *plain_text.last().unwrap();
-    let padding_start: usize = input.len() - (padding_length as usize);
-    
-    let padding = plain_text.split_off(padding_start);
+pub fn encrypt_with_mode(input: Vec<u8>, mode: &mut dyn BlockMode, padding: &dyn Padding) -> Vec<u8> {
+    // pad the input out to full blocks
+    let input = padding.pad(input, mode.block_size());
+    mode.encrypt(&input)
+}
 
-    for value in padding.iter() {
-        if padding_length != (*value) {
-            panic!("Invalid padding")
-        }
+pub fn decrypt_with_mode(input: Vec<u8>, mode: &mut dyn BlockMode, padding: &dyn Padding) -> Result<Vec<u8>, Error> {
+    if input.is_empty() || !input.len().is_multiple_of(mode.block_size()) {
+        return Err(Error::InvalidLength);
     }
 
-    plain_text
+    let plain_text = mode.decrypt(&input);
+
+    // strip and check the padding
+    padding.unpad(plain_text, mode.block_size())
 }
 
 
@@ -217,7 +229,7 @@ mod tests {
             0xE9, 0x81, 0x38, 0x8A,
         ];
 
-        let result = aes_decrypt(input, key);
+        let result = aes_decrypt(input, key).unwrap();
 
         let expected: Vec<u8> = vec![b'A'; 15];
 
@@ -238,7 +250,7 @@ mod tests {
             0x3F, 0x58, 0x28, 0xD8, 
         ];
 
-        let result = aes_decrypt(input, key);
+        let result = aes_decrypt(input, key).unwrap();
 
         let expected: Vec<u8> = vec![b'A'; 16];
 
@@ -255,7 +267,7 @@ mod tests {
             0x2B, 0x7E, 0xD7, 0x9B, 
         ];
 
-        let result = aes_decrypt(input, key);
+        let result = aes_decrypt(input, key).unwrap();
 
         let expected: Vec<u8> = vec![b'A'; 15];
 
@@ -276,7 +288,7 @@ mod tests {
             0x65, 0xF6, 0x93, 0x66, 
         ];
 
-        let result = aes_decrypt(input, key);
+        let result = aes_decrypt(input, key).unwrap();
 
         let expected: Vec<u8> = vec![b'A'; 16];
 
@@ -293,7 +305,7 @@ mod tests {
             0x9B, 0x6A, 0x8A, 0x8A, 
         ];
 
-        let result = aes_decrypt(input, key);
+        let result = aes_decrypt(input, key).unwrap();
 
         let expected: Vec<u8> = vec![b'A'; 15];
 
@@ -314,11 +326,55 @@ mod tests {
             0xAF, 0x02, 0x3B, 0x61, 
         ];
 
-        let result = aes_decrypt(input, key);
+        let result = aes_decrypt(input, key).unwrap();
 
         let expected: Vec<u8> = vec![b'A'; 16];
 
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn decrypt_rejects_zero_pad_byte() {
+        let key: Vec<u8> = vec![b'A'; 16];
+        let key_schedule = aes::key_expansion(key.clone());
+
+        let mut block = vec![b'A'; 16];
+        block[15] = 0x00;
+        let input = aes::cipher(block, 10, &key_schedule);
+
+        assert_eq!(aes_decrypt(input, key), Err(Error::InvalidPadding));
+    }
+
+    #[test]
+    fn decrypt_rejects_oversized_pad_byte() {
+        let key: Vec<u8> = vec![b'A'; 16];
+        let key_schedule = aes::key_expansion(key.clone());
+
+        let mut block = vec![b'A'; 16];
+        block[15] = 0x20;
+        let input = aes::cipher(block, 10, &key_schedule);
+
+        assert_eq!(aes_decrypt(input, key), Err(Error::InvalidPadding));
+    }
+
+    #[test]
+    fn decrypt_rejects_bad_length() {
+        let key: Vec<u8> = vec![b'A'; 16];
+
+        assert_eq!(aes_decrypt(Vec::new(), key.clone()), Err(Error::InvalidLength));
+        assert_eq!(aes_decrypt(vec![0; 15], key), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn round_trip_other_paddings() {
+        let key: Vec<u8> = vec![b'A'; 24];
+        let input: Vec<u8> = vec![b'A'; 21];
+
+        let paddings: [&dyn Padding; 4] = [&padding::AnsiX923, &padding::Iso7816, &padding::Iso10126, &padding::ZeroPadding];
+        for padding in paddings {
+            let encrypted = aes_encrypt_with_padding(input.clone(), key.clone(), padding);
+            assert_eq!(encrypted.len(), 32);
+            assert_eq!(aes_decrypt_with_padding(encrypted, key.clone(), padding), Ok(input.clone()));
+        }
+    }
 }
@@ -2,43 +2,72 @@ mod encryption;
 mod decryption;
 mod util;
 
-use encryption::*;
-use decryption::*;
-use util::*;
+pub use encryption::*;
+pub use decryption::*;
+pub use util::*;
 
 /* ----------- ENCRYPTION AND DECRYPTION ------------ */
-pub fn cipher(input: Vec<u8>, num_rounds: u8, key_schedule: &Vec<Vec<u8>>) -> Vec<u8> {
+// the block size Nb is taken from the input, 4 words for AES and up to 8 for Rijndael
+pub fn cipher(input: Vec<u8>, num_rounds: u8, key_schedule: &[Vec<u8>]) -> Vec<u8> {
+    custom_cipher(input, num_rounds, key_schedule, &Components::AES)
+}
+
+// the AES rounds with the S-box and MixColumns matrix taken from components
+pub fn custom_cipher(input: Vec<u8>, num_rounds: u8, key_schedule: &[Vec<u8>], components: &Components) -> Vec<u8> {
     let num_rounds = num_rounds as usize;
+    let n_b = input.len() / 4;
 
     let mut state = input;
-    state = add_round_key(state, &key_schedule[0..4]);
+    state = add_round_key(state, &key_schedule[0..n_b]);
 
     for round in 1..num_rounds {
-        state = sub_bytes(state);
+        state = substitute(state, &components.s_box);
         state = shift_rows(state);
-        state = mix_columns(state);
-        state = add_round_key(state, &key_schedule[round * 4..round * 4 + 4]);
+        state = multiply_columns(state, &components.mix_columns);
+        state = add_round_key(state, &key_schedule[round * n_b..(round + 1) * n_b]);
     }
     // don't mix columns in final round
-    state = sub_bytes(state);
+    state = substitute(state, &components.s_box);
     state = shift_rows(state);
-    state = add_round_key(state, &key_schedule[num_rounds * 4..num_rounds * 4 + 4]);
+    state = add_round_key(state, &key_schedule[num_rounds * n_b..(num_rounds + 1) * n_b]);
     state
 }
 
-pub fn key_expansion(key: Vec<u8>) -> Vec<Vec<u8>> {
-    let n_k = key.len() / 4;
-    let n_r: u8 = match n_k {
+pub fn num_rounds(n_k: usize) -> u8 {
+    match n_k {
         4 => 10,
         6 => 12,
         8 => 14,
         _ => panic!("Invalid key size!")
-    };
+    }
+}
+
+// Nr = max(Nk, Nb) + 6
+pub fn rijndael_num_rounds(n_k: usize, n_b: usize) -> u8 {
+    if !(4..=8).contains(&n_k) {
+        panic!("Invalid key size!")
+    }
+    if !(4..=8).contains(&n_b) {
+        panic!("Invalid block size!")
+    }
+    (n_k.max(n_b) + 6) as u8
+}
+
+pub fn key_expansion(key: Vec<u8>) -> Vec<Vec<u8>> {
+    // only check the key is a valid AES size
+    num_rounds(key.len() / 4);
+    rijndael_key_expansion(key, 4)
+}
+
+// key schedule with n_b words per round key
+pub fn rijndael_key_expansion(key: Vec<u8>, n_b: usize) -> Vec<Vec<u8>> {
+    let n_k = key.len() / 4;
+    let n_r = rijndael_num_rounds(n_k, n_b);
 
     let mut key_schedule: Vec<Vec<u8>> = Vec::new();
 
     // fill in the first section with the key
-    for word in 0..n_k as usize {
+    for word in 0..n_k {
         let mut temp = Vec::new();
         for byte in 0..4 {
             temp.push(key[word * 4 + byte]);
@@ -47,34 +76,114 @@ pub fn key_expansion(key: Vec<u8>) -> Vec<Vec<u8>> {
     }
 
     // calculate the rest of the sections
-    for i in n_k as usize..4 * (n_r + 1) as usize {
+    for i in n_k..n_b * (n_r + 1) as usize {
         let mut temp = key_schedule[i - 1].clone();
         if i % n_k == 0 {
             temp = sub_word(&rot_word(&temp[..])[..]);
-            temp = gf_add_word(temp, r_con((i / n_k as usize) - 1));
+            temp = gf_add_word(temp, r_con((i / n_k) - 1));
         } else if n_k > 6 && i % n_k == 4 {
             temp = sub_word(&temp[..]);
         }
-        key_schedule.push(gf_add_word(key_schedule[i - n_k as usize].clone(), temp))
+        key_schedule.push(gf_add_word(key_schedule[i - n_k].clone(), temp))
     }
 
     key_schedule
 }
 
 
-pub fn inv_cipher(input: Vec<u8>, num_rounds: u8, key_schedule: &Vec<Vec<u8>>) -> Vec<u8> {
+pub fn inv_cipher(input: Vec<u8>, num_rounds: u8, key_schedule: &[Vec<u8>]) -> Vec<u8> {
+    custom_inv_cipher(input, num_rounds, key_schedule, &Components::AES)
+}
+
+pub fn custom_inv_cipher(input: Vec<u8>, num_rounds: u8, key_schedule: &[Vec<u8>], components: &Components) -> Vec<u8> {
+    let n_b = input.len() / 4;
     let mut state = input;
     let num_rounds = num_rounds as usize;
-    state = add_round_key(state, &key_schedule[4*num_rounds..4*(num_rounds+1)]);
+    state = add_round_key(state, &key_schedule[n_b*num_rounds..n_b*(num_rounds+1)]);
     for round in (1..num_rounds).rev() {
         state = inv_shift_rows(state);
-        state = inv_sub_bytes(state);
-        state = add_round_key(state, &key_schedule[4*round..4*(round+1)]);
-        state = inv_mix_columns(state);
+        state = substitute(state, &components.inv_s_box);
+        state = add_round_key(state, &key_schedule[n_b*round..n_b*(round+1)]);
+        state = multiply_columns(state, &components.inv_mix_columns);
     }
     state = inv_shift_rows(state);
-    state = inv_sub_bytes(state);
-    state = add_round_key(state, &key_schedule[0..4]);
+    state = substitute(state, &components.inv_s_box);
+    state = add_round_key(state, &key_schedule[0..n_b]);
+    state
+}
+
+
+
+// Runs the key schedule backwards. words are n_k consecutive schedule words
+// starting at word index start, the result is the original cipher key.
+pub fn invert_key_expansion(words: &[Vec<u8>], start: usize) -> Vec<u8> {
+    let n_k = words.len();
+    num_rounds(n_k);
+
+    let mut window: Vec<Vec<u8>> = words.to_vec();
+    for lo in (1..=start).rev() {
+        // w[i - n_k] = w[i] ^ temp, with i the last word in the window
+        let i = lo + n_k - 1;
+        let mut temp = window[n_k - 2].clone();
+        if i.is_multiple_of(n_k) {
+            temp = sub_word(&rot_word(&temp[..])[..]);
+            temp = gf_add_word(temp, r_con((i / n_k) - 1));
+        } else if n_k > 6 && i % n_k == 4 {
+            temp = sub_word(&temp[..]);
+        }
+        let previous = gf_add_word(window[n_k - 1].clone(), temp);
+
+        window.pop();
+        window.insert(0, previous);
+    }
+
+    window.concat()
+}
+
+
+
+/* ----------- REDUCED ROUNDS ------------ */
+// Weakened cipher for cryptanalysis experiments, never called from the normal
+// encrypt path. Uses the first num_rounds + 1 round keys of the schedule.
+pub fn reduced_cipher(input: Vec<u8>, num_rounds: u8, key_schedule: &[Vec<u8>], final_mix_columns: bool) -> Vec<u8> {
+    round_states(input, num_rounds, key_schedule, final_mix_columns).pop().unwrap()
+}
+
+// the state after the initial AddRoundKey and after each of the num_rounds rounds
+pub fn round_states(input: Vec<u8>, num_rounds: u8, key_schedule: &[Vec<u8>], final_mix_columns: bool) -> Vec<Vec<u8>> {
+    let num_rounds = num_rounds as usize;
+    let n_b = input.len() / 4;
+
+    let mut state = input;
+    state = add_round_key(state, &key_schedule[0..n_b]);
+    let mut states = vec![state.clone()];
+
+    for round in 1..=num_rounds {
+        state = sub_bytes(state);
+        state = shift_rows(state);
+        if round < num_rounds || final_mix_columns {
+            state = mix_columns(state);
+        }
+        state = add_round_key(state, &key_schedule[round * n_b..(round + 1) * n_b]);
+        states.push(state.clone());
+    }
+    states
+}
+
+pub fn reduced_inv_cipher(input: Vec<u8>, num_rounds: u8, key_schedule: &[Vec<u8>], final_mix_columns: bool) -> Vec<u8> {
+    let num_rounds = num_rounds as usize;
+    let n_b = input.len() / 4;
+
+    let mut state = input;
+    for round in (1..=num_rounds).rev() {
+        state = add_round_key(state, &key_schedule[round * n_b..(round + 1) * n_b]);
+        if round < num_rounds || final_mix_columns {
+            state = inv_mix_columns(state);
+        }
+        state = inv_shift_rows(state);
+        state = inv_sub_bytes(state);
+    }
+    state = add_round_key(state, &key_schedule[0..n_b]);
     state
 }
 
@@ -107,6 +216,18 @@ mod tests {
         assert_eq!(expected, output);
     }
 
+    #[test]
+    fn round_states_test() {
+        let key_schedule = key_expansion(hex::decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap());
+        let states = round_states(hex::decode("3243f6a8885a308d313198a2e0370734").unwrap(), 10, &key_schedule, false);
+
+        // FIPS-197 appendix B, the start of round 2 and the output
+        assert_eq!(states.len(), 11);
+        assert_eq!(states[0], hex::decode("193de3bea0f4e22b9ac68d2ae9f84808").unwrap());
+        assert_eq!(states[1], hex::decode("a49c7ff2689f352b6b5bea43026a5049").unwrap());
+        assert_eq!(states[10], hex::decode("3925841d02dc09fbdc118597196a0b32").unwrap());
+    }
+
     #[test]
     fn inv_cipher_test_official() {
         let expected: Vec<u8> = vec![
@@ -324,8 +445,31 @@ mod tests {
         assert_eq!(key_expansion(key), expected);
     }
 
+    #[test]
+    fn invert_key_expansion_test() {
+        for key in [vec![0x2b; 16], vec![0x8e; 24], vec![0x60; 32]] {
+            let n_k = key.len() / 4;
+            let key_schedule = key_expansion(key.clone());
+
+            // from the last n_k words and from somewhere in the middle
+            let last = key_schedule.len() - n_k;
+            assert_eq!(invert_key_expansion(&key_schedule[last..], last), key);
+            assert_eq!(invert_key_expansion(&key_schedule[9..9 + n_k], 9), key);
+        }
+    }
+
     #[test]
     fn sub_bytes_test() {
 
     }
+
+    #[test]
+    fn mix_columns_test() {
+        // the usual worked example columns
+        let state = hex::decode("db135345f20a225c01010101c6c6c6c6").unwrap();
+        let mixed = mix_columns(state.clone());
+        assert_eq!(hex::encode(&mixed), "8e4da1bc9fdc589d01010101c6c6c6c6");
+        assert_eq!(inv_mix_columns(mixed), state);
+        assert_eq!(gf_mult(0x57, 0x13), 0xfe);
+    }
 }
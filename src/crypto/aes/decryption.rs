@@ -2,42 +2,28 @@ use super::util::*;
 
 /* ----------- DECRYPTION FUNCTIONS ------------ */
 pub fn inv_shift_rows(mut state: Vec<u8>) -> Vec<u8> {
+    let n_b = state.len() / 4;
     for row in 1..4 {
+        let offset = shift_offset(n_b, row);
         let mut new_row = Vec::new();
-        for column in 0..4 {
-            let new_column = (4 + column - row) % 4;
+        for column in 0..n_b {
+            let new_column = (n_b + column - offset) % n_b;
             new_row.push(state[row + new_column * 4]);
         }
-        for column in 0..4 {
-            state[row + column * 4] = new_row[column];
+        for (column, value) in new_row.into_iter().enumerate() {
+            state[row + column * 4] = value;
         }
     }
     state
 }
 
 pub fn inv_sub_bytes(mut state: Vec<u8>) -> Vec<u8> {
-    for i in 0..state.len() {
-        state[i] = inv_s_box(state[i]);
+    for byte in state.iter_mut() {
+        *byte = inv_s_box(*byte);
     }
     state
 }
 
-pub fn inv_mix_columns(mut state: Vec<u8>) -> Vec<u8> {
-    for column in 0..4 {
-        let s0 = state[column * 4];
-        let s1 = state[column * 4 + 1];
-        let s2 = state[column * 4 + 2];
-        let s3 = state[column * 4 + 3];
-
-        // multiply the column elements by a fixed matrix
-        state[column * 4] =
-            gf_mult(0x0e, s0) ^ gf_mult(0x0b, s1) ^ gf_mult(0x0d, s2) ^ gf_mult(0x09, s3);
-        state[column * 4 + 1] =
-            gf_mult(0x09, s0) ^ gf_mult(0x0e, s1) ^ gf_mult(0x0b, s2) ^ gf_mult(0x0d, s3);
-        state[column * 4 + 2] =
-            gf_mult(0x0d, s0) ^ gf_mult(0x09, s1) ^ gf_mult(0x0e, s2) ^ gf_mult(0x0b, s3);
-        state[column * 4 + 3] =
-            gf_mult(0x0b, s0) ^ gf_mult(0x0d, s1) ^ gf_mult(0x09, s2) ^ gf_mult(0x0e, s3);
-    }
-    state
-}
\ No newline at end of file
+pub fn inv_mix_columns(state: Vec<u8>) -> Vec<u8> {
+    multiply_columns(state, &INV_MIX_COLUMNS)
+}
@@ -1,40 +1,10 @@
-const SBOX: [u8; 256] = [
-  0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
-  0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
-  0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
-  0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
-  0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
-  0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
-  0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
-  0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
-  0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
-  0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
-  0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
-  0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
-  0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
-  0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
-  0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
-  0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
-];
+use crate::crypto::gf256::{self, Gf256};
+use crate::crypto::sbox;
 
-const INV_SBOX: [u8; 256] = [
-    0x52, 0x09, 0x6a, 0xd5, 0x30, 0x36, 0xa5, 0x38, 0xbf, 0x40, 0xa3, 0x9e, 0x81, 0xf3, 0xd7, 0xfb,
-    0x7c, 0xe3, 0x39, 0x82, 0x9b, 0x2f, 0xff, 0x87, 0x34, 0x8e, 0x43, 0x44, 0xc4, 0xde, 0xe9, 0xcb,
-    0x54, 0x7b, 0x94, 0x32, 0xa6, 0xc2, 0x23, 0x3d, 0xee, 0x4c, 0x95, 0x0b, 0x42, 0xfa, 0xc3, 0x4e,
-    0x08, 0x2e, 0xa1, 0x66, 0x28, 0xd9, 0x24, 0xb2, 0x76, 0x5b, 0xa2, 0x49, 0x6d, 0x8b, 0xd1, 0x25,
-    0x72, 0xf8, 0xf6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xd4, 0xa4, 0x5c, 0xcc, 0x5d, 0x65, 0xb6, 0x92,
-    0x6c, 0x70, 0x48, 0x50, 0xfd, 0xed, 0xb9, 0xda, 0x5e, 0x15, 0x46, 0x57, 0xa7, 0x8d, 0x9d, 0x84,
-    0x90, 0xd8, 0xab, 0x00, 0x8c, 0xbc, 0xd3, 0x0a, 0xf7, 0xe4, 0x58, 0x05, 0xb8, 0xb3, 0x45, 0x06,
-    0xd0, 0x2c, 0x1e, 0x8f, 0xca, 0x3f, 0x0f, 0x02, 0xc1, 0xaf, 0xbd, 0x03, 0x01, 0x13, 0x8a, 0x6b,
-    0x3a, 0x91, 0x11, 0x41, 0x4f, 0x67, 0xdc, 0xea, 0x97, 0xf2, 0xcf, 0xce, 0xf0, 0xb4, 0xe6, 0x73,
-    0x96, 0xac, 0x74, 0x22, 0xe7, 0xad, 0x35, 0x85, 0xe2, 0xf9, 0x37, 0xe8, 0x1c, 0x75, 0xdf, 0x6e,
-    0x47, 0xf1, 0x1a, 0x71, 0x1d, 0x29, 0xc5, 0x89, 0x6f, 0xb7, 0x62, 0x0e, 0xaa, 0x18, 0xbe, 0x1b,
-    0xfc, 0x56, 0x3e, 0x4b, 0xc6, 0xd2, 0x79, 0x20, 0x9a, 0xdb, 0xc0, 0xfe, 0x78, 0xcd, 0x5a, 0xf4,
-    0x1f, 0xdd, 0xa8, 0x33, 0x88, 0x07, 0xc7, 0x31, 0xb1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xec, 0x5f,
-    0x60, 0x51, 0x7f, 0xa9, 0x19, 0xb5, 0x4a, 0x0d, 0x2d, 0xe5, 0x7a, 0x9f, 0x93, 0xc9, 0x9c, 0xef,
-    0xa0, 0xe0, 0x3b, 0x4d, 0xae, 0x2a, 0xf5, 0xb0, 0xc8, 0xeb, 0xbb, 0x3c, 0x83, 0x53, 0x99, 0x61,
-    0x17, 0x2b, 0x04, 0x7e, 0xba, 0x77, 0xd6, 0x26, 0xe1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0c, 0x7d,
-];
+// worked out by crypto::sbox at compile time rather than written out
+pub const SBOX: [u8; 256] = sbox::generate_s_box();
+
+pub const INV_SBOX: [u8; 256] = sbox::generate_inv_s_box();
 
 // SBOX FUNCTIONS
 pub fn s_box(byte: u8) -> u8 {
@@ -51,8 +21,8 @@ pub fn inv_s_box(byte: u8) -> u8 {
 
 pub fn sub_word(word: &[u8]) -> Vec<u8> {
   let mut new_word: Vec<u8> = Vec::new();
-  for i in 0..word.len() {
-    new_word.push(s_box(word[i]));
+  for byte in word {
+    new_word.push(s_box(*byte));
   }
   new_word
 }
@@ -70,8 +40,17 @@ pub const RCON: [[u8; 4]; 10] = [
   [0x36, 0, 0, 0]
 ];
 
+// Rijndael with wide blocks and short keys runs past the AES table
 pub fn r_con(index: usize) -> Vec<u8> {
-  Vec::from(RCON[index])
+  if index < RCON.len() {
+    return Vec::from(RCON[index]);
+  }
+
+  let mut value = RCON[RCON.len() - 1][0];
+  for _ in RCON.len() - 1..index {
+    value = gf_mult(0x02, value);
+  }
+  vec![value, 0, 0, 0]
 }
 
 pub fn gf_add_word(left: Vec<u8>, right: Vec<u8>) -> Vec<u8> {
@@ -86,25 +65,8 @@ pub fn gf_add_word(left: Vec<u8>, right: Vec<u8>) -> Vec<u8> {
   new_word
 }
 
-pub fn gf_mult(mut a: u8, mut b: u8) -> u8 {
-    let mut result: u8 = 0;
-    let irreducible: u8 = 0b00011011;
-
-    for _ in 0..8 {
-      if b & 1 == 1 {
-        result ^= a;
-      }
-
-      // xor with irreducible if there is a carry
-      let carry = a & 0x80;
-      a <<= 1;
-      if carry != 0 {
-        a ^= irreducible;
-      }
-      b >>= 1;
-    }
-
-    result
+pub fn gf_mult(a: u8, b: u8) -> u8 {
+  (Gf256::new(a) * Gf256::new(b)).value()
 }
 
 
@@ -119,10 +81,101 @@ pub fn rot_word(word: &[u8]) -> Vec<u8> {
 }
 
 pub fn add_round_key(mut state: Vec<u8>, round_key: &[Vec<u8>]) -> Vec<u8> {
-    for column in 0..4 {
+    for (column, word) in round_key.iter().enumerate() {
         for row in 0..4 {
-            state[row + column * 4] ^= round_key[column][row];
+            state[row + column * 4] ^= word[row];
+        }
+    }
+    state
+}
+
+// ShiftRows offset for each row, Nb = 4 gives the AES offsets
+pub fn shift_offset(n_b: usize, row: usize) -> usize {
+    match (n_b, row) {
+        (7, 3) => 4,
+        (8, 2) => 3,
+        (8, 3) => 4,
+        _ => row,
+    }
+}
+
+// MixColumns treats each column as a vector over GF(2^8) and multiplies it by this matrix
+pub const MIX_COLUMNS: [[u8; 4]; 4] = [[0x02, 0x03, 0x01, 0x01], [0x01, 0x02, 0x03, 0x01], [0x01, 0x01, 0x02, 0x03], [0x03, 0x01, 0x01, 0x02]];
+
+pub const INV_MIX_COLUMNS: [[u8; 4]; 4] = [[0x0e, 0x0b, 0x0d, 0x09], [0x09, 0x0e, 0x0b, 0x0d], [0x0d, 0x09, 0x0e, 0x0b], [0x0b, 0x0d, 0x09, 0x0e]];
+
+// the S-box and MixColumns matrix the rounds run with, with their inverses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Components {
+    pub s_box: [u8; 256],
+    pub inv_s_box: [u8; 256],
+    pub mix_columns: [[u8; 4]; 4],
+    pub inv_mix_columns: [[u8; 4]; 4],
+}
+
+impl Components {
+    // the tables cipher and inv_cipher use
+    pub const AES: Components = Components {
+        s_box: SBOX,
+        inv_s_box: INV_SBOX,
+        mix_columns: MIX_COLUMNS,
+        inv_mix_columns: INV_MIX_COLUMNS,
+    };
+
+    pub fn aes() -> Components {
+        Components::AES
+    }
+
+    // the inverses are worked out, so the S-box has to be a permutation
+    // and the matrix invertible
+    pub fn new(s_box: [u8; 256], mix_columns: [[u8; 4]; 4]) -> Components {
+        let mut inv_s_box = [0; 256];
+        let mut seen = [false; 256];
+        for (input, &output) in s_box.iter().enumerate() {
+            if seen[output as usize] {
+                panic!("S-box is not a permutation");
+            }
+            seen[output as usize] = true;
+            inv_s_box[output as usize] = input as u8;
+        }
+
+        let rows: Vec<Vec<u8>> = mix_columns.iter().map(|row| row.to_vec()).collect();
+        let Some(inverse) = gf256::invert_matrix(&rows, gf256::AES_POLYNOMIAL) else {
+            panic!("MixColumns matrix is not invertible");
+        };
+        let mut inv_mix_columns = [[0; 4]; 4];
+        for (row, inverse_row) in inv_mix_columns.iter_mut().zip(inverse) {
+            row.copy_from_slice(&inverse_row);
+        }
+
+        Components {
+            s_box,
+            inv_s_box,
+            mix_columns,
+            inv_mix_columns,
         }
     }
+}
+
+pub fn multiply_columns(mut state: Vec<u8>, matrix: &[[u8; 4]; 4]) -> Vec<u8> {
+    for column in state.chunks_mut(4) {
+        let s = [0, 1, 2, 3].map(|row| Gf256::new(column[row]));
+        for (row, coefficients) in matrix.iter().enumerate() {
+            let mut sum = Gf256::new(0);
+            for (&coefficient, &element) in coefficients.iter().zip(&s) {
+                // Mul loops over the bits of its right operand, so the small constant goes there
+                sum += element * Gf256::new(coefficient);
+            }
+            column[row] = sum.value();
+        }
+    }
+    state
+}
+
+// SubBytes with any substitution table in place of the AES S-box
+pub fn substitute(mut state: Vec<u8>, table: &[u8; 256]) -> Vec<u8> {
+    for byte in state.iter_mut() {
+        *byte = table[*byte as usize];
+    }
     state
 }
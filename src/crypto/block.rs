@@ -0,0 +1,140 @@
+use super::aes;
+
+// A keyed cipher working on single fixed size blocks
+pub trait BlockCipher {
+    fn block_size(&self) -> usize;
+    fn encrypt_block(&self, block: Vec<u8>) -> Vec<u8>;
+    fn decrypt_block(&self, block: Vec<u8>) -> Vec<u8>;
+}
+
+// A way of chaining block cipher calls over input made of whole blocks,
+// padding or stealing is left to the caller
+pub trait BlockMode {
+    fn block_size(&self) -> usize;
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8>;
+    fn decrypt(&mut self, input: &[u8]) -> Vec<u8>;
+}
+
+/* ----------- AES ------------ */
+// AES-128, AES-192 or AES-256 depending on the key length
+#[derive(Clone)]
+pub struct Aes {
+    num_rounds: u8,
+    key_schedule: Vec<Vec<u8>>,
+}
+
+impl Aes {
+    pub fn new(key: Vec<u8>) -> Aes {
+        if ![16, 24, 32].contains(&key.len()) {
+            panic!("Invalid key length");
+        }
+
+        let num_rounds = aes::num_rounds(key.len() / 4);
+
+        Aes {
+            num_rounds,
+            key_schedule: aes::key_expansion(key),
+        }
+    }
+}
+
+impl BlockCipher for Aes {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn encrypt_block(&self, block: Vec<u8>) -> Vec<u8> {
+        aes::cipher(block, self.num_rounds, &self.key_schedule)
+    }
+
+    fn decrypt_block(&self, block: Vec<u8>) -> Vec<u8> {
+        aes::inv_cipher(block, self.num_rounds, &self.key_schedule)
+    }
+}
+
+/* ----------- ECB ------------ */
+pub struct Ecb<C: BlockCipher> {
+    cipher: C,
+}
+
+impl<C: BlockCipher> Ecb<C> {
+    pub fn new(cipher: C) -> Ecb<C> {
+        Ecb { cipher }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Ecb<C> {
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut cipher_text: Vec<u8> = Vec::new();
+        for block in input.chunks(self.block_size()) {
+            let mut encrypted = self.cipher.encrypt_block(block.to_vec());
+            cipher_text.append(&mut encrypted);
+        }
+        cipher_text
+    }
+
+    fn decrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut plain_text: Vec<u8> = Vec::new();
+        for block in input.chunks(self.block_size()) {
+            let mut decrypted = self.cipher.decrypt_block(block.to_vec());
+            plain_text.append(&mut decrypted);
+        }
+        plain_text
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::cbc::Cbc;
+
+    // known answer plus a round trip through every mode
+    fn check_cipher<C: BlockCipher + Clone + 'static>(cipher: C, plain_text: &str, expected: &str) {
+        let plain_text = hex::decode(plain_text).unwrap();
+        let expected = hex::decode(expected).unwrap();
+
+        assert_eq!(cipher.block_size(), plain_text.len());
+        assert_eq!(cipher.encrypt_block(plain_text.clone()), expected);
+        assert_eq!(cipher.decrypt_block(expected), plain_text);
+
+        let input: Vec<u8> = (0..cipher.block_size() * 4).map(|i| i as u8).collect();
+        let iv = vec![0x5a; cipher.block_size()];
+        let modes: Vec<(Box<dyn BlockMode>, Box<dyn BlockMode>)> = vec![
+            (Box::new(Ecb::new(cipher.clone())), Box::new(Ecb::new(cipher.clone()))),
+            (Box::new(Cbc::new(cipher.clone(), iv.clone())), Box::new(Cbc::new(cipher.clone(), iv))),
+        ];
+
+        for (mut encryptor, mut decryptor) in modes {
+            let encrypted = encryptor.encrypt(&input);
+            assert_eq!(encrypted.len(), input.len());
+            assert_ne!(encrypted, input);
+            assert_eq!(decryptor.decrypt(&encrypted), input);
+        }
+    }
+
+    // FIPS-197 appendix C
+    #[test]
+    fn aes_128() {
+        let key: Vec<u8> = (0..16).collect();
+        check_cipher(Aes::new(key), "00112233445566778899aabbccddeeff", "69c4e0d86a7b0430d8cdb78070b4c55a");
+    }
+
+    #[test]
+    fn aes_192() {
+        let key: Vec<u8> = (0..24).collect();
+        check_cipher(Aes::new(key), "00112233445566778899aabbccddeeff", "dda97ca4864cdfe06eaf70a0ec0d7191");
+    }
+
+    #[test]
+    fn aes_256() {
+        let key: Vec<u8> = (0..32).collect();
+        check_cipher(Aes::new(key), "00112233445566778899aabbccddeeff", "8ea2b7ca516745bfeafc49904b496089");
+    }
+}
@@ -0,0 +1,553 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::block::{Aes, BlockMode, Ecb};
+use super::cbc::Cbc;
+use super::cfb::{Cfb, Segment};
+use super::ofb::Ofb;
+
+// Runs NIST CAVP response files (AESAVS) against the library. A file holds
+// [ENCRYPT] and [DECRYPT] sections of numbered vectors:
+//
+//   COUNT = 0
+//   KEY = 00000000000000000000000000000000
+//   IV = 00000000000000000000000000000000
+//   PLAINTEXT = f34481ec3cc627bacd5dc3fb08f273e6
+//   CIPHERTEXT = 0336763e966d92595a567cc9ce537f5e
+//
+// The mode and the kind of test come from the file name, as in ECBGFSbox128.rsp
+// or CFB8MCT256.rsp. Monte Carlo files list the inputs and final output of
+// each outer iteration, the inner 1000 iterations have to be rerun to check them.
+
+const MCT_ITERATIONS: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Ecb,
+    Cbc,
+    Cfb8,
+    Cfb128,
+    Ofb,
+}
+
+impl Mode {
+    // CFB1 files work on single bits which the library's CFB1 can't take,
+    // run_directory lists them as skipped
+    pub fn from_file_name(name: &str) -> Option<Mode> {
+        let prefixes = [
+            ("ECB", Mode::Ecb),
+            ("CBC", Mode::Cbc),
+            ("CFB8", Mode::Cfb8),
+            ("CFB128", Mode::Cfb128),
+            ("OFB", Mode::Ofb),
+        ];
+        prefixes.iter().find(|(prefix, _)| name.starts_with(prefix)).map(|&(_, mode)| mode)
+    }
+
+    // bytes processed per step of the Monte Carlo test
+    fn unit(&self) -> usize {
+        match self {
+            Mode::Cfb8 => 1,
+            _ => 16,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Encrypt,
+    Decrypt,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vector {
+    pub count: usize,
+    pub key: Vec<u8>,
+    // empty for ECB
+    pub iv: Vec<u8>,
+    pub plain_text: Vec<u8>,
+    pub cipher_text: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub direction: Direction,
+    pub vectors: Vec<Vector>,
+}
+
+/* ----------- PARSING ------------ */
+pub fn parse(text: &str) -> io::Result<Vec<Section>> {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut current: Option<Vector> = None;
+
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') {
+            continue;
+        }
+        if line.is_empty() {
+            finish(&mut sections, &mut current);
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            finish(&mut sections, &mut current);
+            let direction = match header {
+                "ENCRYPT" => Direction::Encrypt,
+                "DECRYPT" => Direction::Decrypt,
+                _ => return Err(invalid_data(format!("Unknown section [{}]", header))),
+            };
+            sections.push(Section {
+                direction,
+                vectors: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some((name, value)) = line.split_once('=') else {
+            return Err(invalid_data(format!("Expected NAME = value, found {}", line)));
+        };
+        let (name, value) = (name.trim(), value.trim());
+
+        if name == "COUNT" {
+            finish(&mut sections, &mut current);
+            let count = value.parse().map_err(|_| invalid_data(format!("Invalid COUNT {}", value)))?;
+            current = Some(Vector {
+                count,
+                ..Vector::default()
+            });
+            continue;
+        }
+
+        let Some(vector) = current.as_mut() else {
+            return Err(invalid_data(format!("{} outside of a vector", name)));
+        };
+        let bytes = hex::decode(value).map_err(|_| invalid_data(format!("Invalid hex for {}", name)))?;
+        match name {
+            "KEY" => vector.key = bytes,
+            "IV" => vector.iv = bytes,
+            "PLAINTEXT" => vector.plain_text = bytes,
+            "CIPHERTEXT" => vector.cipher_text = bytes,
+            _ => return Err(invalid_data(format!("Unknown field {}", name))),
+        }
+    }
+
+    finish(&mut sections, &mut current);
+    Ok(sections)
+}
+
+fn finish(sections: &mut [Section], current: &mut Option<Vector>) {
+    if let Some(vector) = current.take() {
+        if let Some(section) = sections.last_mut() {
+            section.vectors.push(vector);
+        }
+    }
+}
+
+/* ----------- RUNNING ------------ */
+// the mode kept running across calls, as the Monte Carlo test needs
+type Stream = Box<dyn FnMut(&[u8]) -> Vec<u8>>;
+
+fn stream(mode: Mode, direction: Direction, key: &[u8], iv: &[u8]) -> Stream {
+    let (key, iv) = (key.to_vec(), iv.to_vec());
+    let encrypting = direction == Direction::Encrypt;
+    match mode {
+        Mode::Ecb => {
+            let mut ecb = Ecb::new(Aes::new(key));
+            Box::new(move |input| if encrypting { ecb.encrypt(input) } else { ecb.decrypt(input) })
+        }
+        Mode::Cbc => {
+            let mut cbc = Cbc::new(Aes::new(key), iv);
+            Box::new(move |input| if encrypting { cbc.encrypt(input) } else { cbc.decrypt(input) })
+        }
+        Mode::Cfb8 | Mode::Cfb128 => {
+            let segment = if mode == Mode::Cfb8 { Segment::Bits8 } else { Segment::Bits128 };
+            let mut cfb = Cfb::new(key, iv, segment);
+            Box::new(move |input| if encrypting { cfb.encrypt(input) } else { cfb.decrypt(input) })
+        }
+        Mode::Ofb => {
+            let mut ofb = Ofb::new(key, iv);
+            Box::new(move |input| ofb.apply_keystream(input))
+        }
+    }
+}
+
+// the input and expected output of a vector in the given direction
+fn input_output(vector: &Vector, direction: Direction) -> (&[u8], &[u8]) {
+    match direction {
+        Direction::Encrypt => (&vector.plain_text, &vector.cipher_text),
+        Direction::Decrypt => (&vector.cipher_text, &vector.plain_text),
+    }
+}
+
+pub fn known_answer(mode: Mode, direction: Direction, vector: &Vector) -> bool {
+    let (input, output) = input_output(vector, direction);
+    stream(mode, direction, &vector.key, &vector.iv)(input) == output
+}
+
+// The AESAVS Monte Carlo test from the inputs of the first vector, returns
+// outer_iterations vectors to compare against the file. Each outer iteration
+// runs the mode 1000 times feeding outputs back in, then derives the next key
+// from the last outputs.
+pub fn monte_carlo(mode: Mode, direction: Direction, first: &Vector, outer_iterations: usize) -> Vec<Vector> {
+    let (input, _) = input_output(first, direction);
+    let mut key = first.key.clone();
+    let mut iv = first.iv.clone();
+    let mut input = input.to_vec();
+    let mut vectors = Vec::new();
+
+    for count in 0..outer_iterations {
+        let mut process = stream(mode, direction, &key, &iv);
+        let mut outputs: Vec<Vec<u8>> = Vec::with_capacity(MCT_ITERATIONS);
+        let first_input = input.clone();
+
+        for j in 0..MCT_ITERATIONS {
+            outputs.push(process(&input));
+            input = match mode {
+                Mode::Ecb => outputs[j].clone(),
+                Mode::Cfb8 if j < 16 => vec![iv[j]],
+                Mode::Cfb8 => outputs[j - 16].clone(),
+                _ if j == 0 => iv.clone(),
+                _ => outputs[j - 1].clone(),
+            };
+        }
+
+        let last = outputs[MCT_ITERATIONS - 1].clone();
+        let (plain_text, cipher_text) = match direction {
+            Direction::Encrypt => (first_input, last.clone()),
+            Direction::Decrypt => (last.clone(), first_input),
+        };
+        vectors.push(Vector {
+            count,
+            key: key.clone(),
+            iv: iv.clone(),
+            plain_text,
+            cipher_text,
+        });
+
+        // xor the key with as many of the last output bytes as it is long
+        let tail: Vec<u8> = outputs.concat().split_off(MCT_ITERATIONS * mode.unit() - key.len());
+        key = super::xor(&key, &tail);
+
+        match mode {
+            Mode::Ecb => input = last,
+            Mode::Cfb8 => {
+                iv = tail[tail.len() - 16..].to_vec();
+                input = outputs[MCT_ITERATIONS - 17].clone();
+            }
+            _ => {
+                iv = last;
+                input = outputs[MCT_ITERATIONS - 2].clone();
+            }
+        }
+    }
+    vectors
+}
+
+/* ----------- FILES ------------ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileResult {
+    pub name: String,
+    pub vectors: usize,
+    // (direction, count) of every vector that didn't match
+    pub failures: Vec<(Direction, usize)>,
+}
+
+impl FileResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+// the files run_directory ran, and the names of those in a mode it can't run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirectoryResult {
+    pub files: Vec<FileResult>,
+    pub skipped: Vec<String>,
+}
+
+// None for files of a mode this can't run
+pub fn run_file(path: &Path) -> io::Result<Option<FileResult>> {
+    let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default().to_string();
+    let Some(mode) = Mode::from_file_name(&name) else {
+        return Ok(None);
+    };
+    let sections = parse(&fs::read_to_string(path)?)?;
+    let monte_carlo_file = name.contains("MCT");
+
+    for vector in sections.iter().flat_map(|section| &section.vectors) {
+        check_lengths(mode, monte_carlo_file, vector).map_err(|message| invalid_data(format!("{} COUNT = {}: {}", name, vector.count, message)))?;
+    }
+
+    let mut vectors = 0;
+    let mut failures = Vec::new();
+    for section in &sections {
+        vectors += section.vectors.len();
+        if monte_carlo_file {
+            let Some(first) = section.vectors.first() else {
+                continue;
+            };
+            let expected = monte_carlo(mode, section.direction, first, section.vectors.len());
+            for (vector, computed) in section.vectors.iter().zip(&expected) {
+                if vector != computed {
+                    failures.push((section.direction, vector.count));
+                }
+            }
+        } else {
+            for vector in &section.vectors {
+                if !known_answer(mode, section.direction, vector) {
+                    failures.push((section.direction, vector.count));
+                }
+            }
+        }
+    }
+
+    Ok(Some(FileResult {
+        name,
+        vectors,
+        failures,
+    }))
+}
+
+// the modes and monte_carlo would panic on these, so they are reported as a
+// bad file instead
+fn check_lengths(mode: Mode, monte_carlo: bool, vector: &Vector) -> Result<(), &'static str> {
+    if ![16, 24, 32].contains(&vector.key.len()) {
+        return Err("KEY must be 128, 192 or 256 bits");
+    }
+    if mode != Mode::Ecb && vector.iv.len() != 16 {
+        return Err("IV must be 128 bits");
+    }
+    let texts = [&vector.plain_text, &vector.cipher_text];
+    if texts.iter().any(|text| text.is_empty()) {
+        return Err("PLAINTEXT and CIPHERTEXT must not be empty");
+    }
+    // each Monte Carlo step takes one unit, a block or a CFB8 byte
+    if monte_carlo && texts.iter().any(|text| text.len() != mode.unit()) {
+        return Err("Monte Carlo PLAINTEXT and CIPHERTEXT must be one block, or one byte for CFB8");
+    }
+    let block_mode = matches!(mode, Mode::Ecb | Mode::Cbc);
+    if block_mode && texts.iter().any(|text| !text.len().is_multiple_of(16)) {
+        return Err("PLAINTEXT and CIPHERTEXT must be whole blocks");
+    }
+    Ok(())
+}
+
+// every .rsp file in the directory, by name
+pub fn response_files(directory: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths: Vec<_> = fs::read_dir(directory)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "rsp"));
+    paths.sort();
+    Ok(paths)
+}
+
+// runs the response files whose names pass the filter, files of a mode
+// run_file can't take are listed as skipped rather than dropped
+pub fn run_directory(directory: &Path, filter: impl Fn(&str) -> bool) -> io::Result<DirectoryResult> {
+    let mut results = DirectoryResult::default();
+    for path in response_files(directory)? {
+        let name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if !filter(name) {
+            continue;
+        }
+        match run_file(&path)? {
+            Some(result) => results.files.push(result),
+            None => results.skipped.push(name.to_string()),
+        }
+    }
+    Ok(results)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "# CAVS 11.1\r\n\
+                          # Config info for aes_values\r\n\
+                          \r\n\
+                          [ENCRYPT]\r\n\
+                          \r\n\
+                          COUNT = 0\r\n\
+                          KEY = 00000000000000000000000000000000\r\n\
+                          PLAINTEXT = f34481ec3cc627bacd5dc3fb08f273e6\r\n\
+                          CIPHERTEXT = 0336763e966d92595a567cc9ce537f5e\r\n\
+                          \r\n\
+                          [DECRYPT]\r\n\
+                          \r\n\
+                          COUNT = 0\r\n\
+                          KEY = 00000000000000000000000000000000\r\n\
+                          CIPHERTEXT = 0336763e966d92595a567cc9ce537f5e\r\n\
+                          PLAINTEXT = f34481ec3cc627bacd5dc3fb08f273e6\r\n";
+
+    fn vectors_directory() -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("vectors/cavp")
+    }
+
+    #[test]
+    fn parses_response_file() {
+        let sections = parse(SAMPLE).unwrap();
+
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[1].direction, Direction::Decrypt);
+        let vector = &sections[0].vectors[0];
+        assert_eq!(vector.key, vec![0; 16]);
+        assert!(vector.iv.is_empty());
+        assert_eq!(hex::encode(&vector.cipher_text), "0336763e966d92595a567cc9ce537f5e");
+
+        assert!(known_answer(Mode::Ecb, Direction::Encrypt, vector));
+        assert!(known_answer(Mode::Ecb, Direction::Decrypt, &sections[1].vectors[0]));
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(parse("[ENCRYPT]\nCOUNT = 0\nKEY = 0g\n").is_err());
+        assert!(parse("[ENCRYPT]\nKEY = 00\n").is_err());
+        assert!(parse("[MONTE]\n").is_err());
+        assert!(parse("[ENCRYPT]\nCOUNT = 0\nNONCE = 00\n").is_err());
+    }
+
+    #[test]
+    fn mode_from_file_name() {
+        assert_eq!(Mode::from_file_name("CFB8MCT256.rsp"), Some(Mode::Cfb8));
+        assert_eq!(Mode::from_file_name("CFB128VarTxt128.rsp"), Some(Mode::Cfb128));
+        assert_eq!(Mode::from_file_name("OFBGFSbox128.rsp"), Some(Mode::Ofb));
+        assert_eq!(Mode::from_file_name("CFB1VarKey128.rsp"), None);
+    }
+
+    #[test]
+    fn rejects_bad_lengths() {
+        let directory = std::env::temp_dir().join(format!("lock_talk_cavp_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        let ofb = SAMPLE.replace("KEY = ", "IV = 00000000000000000000000000000000\r\nKEY = ");
+        let files = [
+            ("ECBVarKey100.rsp", "[ENCRYPT]\nCOUNT = 0\nKEY = 0000\nPLAINTEXT = 00\nCIPHERTEXT = 00\n"),
+            ("CBCVarTxt128.rsp", &SAMPLE.replace("KEY = ", "IV = 00\r\nKEY = ")),
+            ("OFBVarTxt128.rsp", SAMPLE),
+            ("ECBGFSbox128.rsp", &SAMPLE.replace("f34481ec3cc627bacd5dc3fb08f273e6", "f34481ec")),
+            ("OFBVarKey128.rsp", &ofb.replace("PLAINTEXT = f34481ec3cc627bacd5dc3fb08f273e6", "PLAINTEXT = ")),
+            // a Monte Carlo vector without its input, or with more than a block of it
+            ("ECBMCT128.rsp", "[ENCRYPT]\nCOUNT = 0\nKEY = 00000000000000000000000000000000\n"),
+            ("ECBMCT192.rsp", &SAMPLE.replace("f34481ec3cc627bacd5dc3fb08f273e6", &"f34481ec3cc627bacd5dc3fb08f273e6".repeat(2))),
+            ("CFB8MCT128.rsp", &ofb),
+        ];
+        for (name, text) in files {
+            let path = directory.join(name);
+            fs::write(&path, text).unwrap();
+            let error = run_file(&path).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", name);
+        }
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn reports_skipped_files() {
+        let directory = std::env::temp_dir().join(format!("lock_talk_cavp_skipped_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("CFB1GFSbox128.rsp"), "[ENCRYPT]\nCOUNT = 0\nKEY = 00\nPLAINTEXT = 0\n").unwrap();
+        fs::write(directory.join("ECBGFSbox128.rsp"), SAMPLE).unwrap();
+
+        let results = run_directory(&directory, |_| true).unwrap();
+        assert_eq!(results.files.len(), 1);
+        assert!(results.files[0].passed());
+        assert_eq!(results.skipped, vec!["CFB1GFSbox128.rsp"]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn multi_block_files() {
+        let results = run_directory(&vectors_directory(), |name| name.contains("MMT")).unwrap().files;
+
+        // 5 modes and 3 key sizes
+        assert_eq!(results.len(), 15);
+        for result in &results {
+            assert_eq!(result.vectors, 20, "{}", result.name);
+            assert!(result.passed(), "{} failed {:?}", result.name, result.failures);
+        }
+    }
+
+    // NIST's KAT_AES.zip and aesmct.zip, fetched with vectors/cavp/fetch.py
+    #[test]
+    #[ignore = "needs NIST's files, see vectors/cavp/README.md"]
+    fn official_files() {
+        let results = run_directory(&vectors_directory(), |name| !name.contains("MMT")).unwrap();
+
+        // GFSbox, KeySbox, VarKey, VarTxt and MCT for 6 modes and 3 key sizes, less CFB1
+        assert_eq!(results.files.len(), 75);
+        assert_eq!(results.skipped.len(), 15);
+        for result in &results.files {
+            if result.name.contains("MCT") {
+                assert_eq!(result.vectors, 200, "{}", result.name);
+            }
+            assert!(result.vectors > 0, "{} is empty", result.name);
+            assert!(result.passed(), "{} failed {:?}", result.name, result.failures);
+        }
+    }
+
+    // COUNT = 0 of NIST's own ECBMCT128.rsp and CBCMCT128.rsp, so the runner
+    // is checked against NIST even before the files are fetched
+    #[test]
+    fn official_monte_carlo_vectors() {
+        let ecb = Vector {
+            count: 0,
+            key: hex::decode("139a35422f1d61de3c91787fe0507afd").unwrap(),
+            iv: Vec::new(),
+            plain_text: hex::decode("b9145a768b7dc489a096b546f43b231f").unwrap(),
+            cipher_text: hex::decode("d7c3ffac9031238650901e157364c386").unwrap(),
+        };
+        assert_eq!(monte_carlo(Mode::Ecb, Direction::Encrypt, &ecb, 1), vec![ecb]);
+
+        let cbc = Vector {
+            count: 0,
+            key: hex::decode("9dc2c84a37850c11699818605f47958c").unwrap(),
+            iv: hex::decode("256953b2feab2a04ae0180d8335bbed6").unwrap(),
+            plain_text: hex::decode("2e586692e647f5028ec6fa47a55a2aab").unwrap(),
+            cipher_text: hex::decode("1b1ebd1fc45ec43037fd4844241a437f").unwrap(),
+        };
+        assert_eq!(monte_carlo(Mode::Cbc, Direction::Encrypt, &cbc, 1), vec![cbc]);
+    }
+
+    #[test]
+    fn monte_carlo_catches_a_wrong_output() {
+        let first = Vector {
+            key: crate::key_gen(128),
+            plain_text: crate::iv_gen(),
+            ..Vector::default()
+        };
+        let mut vectors = monte_carlo(Mode::Ecb, Direction::Encrypt, &first, 10);
+        vectors[5].cipher_text[0] ^= 1;
+
+        let mut text = String::from("[ENCRYPT]\n");
+        for vector in &vectors {
+            text += &format!(
+                "\nCOUNT = {}\nKEY = {}\nPLAINTEXT = {}\nCIPHERTEXT = {}\n",
+                vector.count,
+                hex::encode(&vector.key),
+                hex::encode(&vector.plain_text),
+                hex::encode(&vector.cipher_text)
+            );
+        }
+        let directory = std::env::temp_dir().join(format!("lock_talk_cavp_mct_{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("ECBMCT128.rsp");
+        fs::write(&path, text).unwrap();
+
+        // every vector is worked out from the first one, so only COUNT = 5 is off
+        let result = run_file(&path).unwrap().unwrap();
+        assert_eq!(result.vectors, 10);
+        assert_eq!(result.failures, vec![(Direction::Encrypt, 5)]);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
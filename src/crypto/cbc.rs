@@ -0,0 +1,281 @@
+use super::block::{Aes, BlockCipher, BlockMode};
+use super::padding::{Padding, Pkcs7};
+use super::{xor, Error};
+
+// ciphertext stealing variants from the SP 800-38A addendum,
+// they only differ in the order of the last two blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cts {
+    // partial block stays in place: ... C_{n-1}* || C_n
+    Cs1,
+    // like Cs3 when there is a partial block, like plain CBC otherwise
+    Cs2,
+    // last two blocks always swapped: ... C_n || C_{n-1}*
+    Cs3,
+}
+
+/* ----------- PADDED CBC ------------ */
+pub fn aes_cbc_encrypt(input: Vec<u8>, key: Vec<u8>, iv: Vec<u8>) -> Vec<u8> {
+    aes_cbc_encrypt_with_padding(input, key, iv, &Pkcs7)
+}
+
+pub fn aes_cbc_decrypt(input: Vec<u8>, key: Vec<u8>, iv: Vec<u8>) -> Result<Vec<u8>, Error> {
+    aes_cbc_decrypt_with_padding(input, key, iv, &Pkcs7)
+}
+
+pub fn aes_cbc_encrypt_with_padding(input: Vec<u8>, key: Vec<u8>, iv: Vec<u8>, padding: &dyn Padding) -> Vec<u8> {
+    let input = padding.pad(input, 16);
+    Cbc::new(Aes::new(key), iv).encrypt(&input)
+}
+
+pub fn aes_cbc_decrypt_with_padding(
+    input: Vec<u8>,
+    key: Vec<u8>,
+    iv: Vec<u8>,
+    padding: &dyn Padding,
+) -> Result<Vec<u8>, Error> {
+    if input.is_empty() || !input.len().is_multiple_of(16) {
+        return Err(Error::InvalidLength);
+    }
+
+    let plain_text = Cbc::new(Aes::new(key), iv).decrypt(&input);
+    padding.unpad(plain_text, 16)
+}
+
+/* ----------- CIPHERTEXT STEALING ------------ */
+// ciphertext is the same length as the input, which must be at least one block
+pub fn aes_cbc_cts_encrypt(input: Vec<u8>, key: Vec<u8>, iv: Vec<u8>, variant: Cts) -> Vec<u8> {
+    if input.len() < 16 {
+        panic!("Invalid input length");
+    }
+
+    let mut cbc = Cbc::new(Aes::new(key), iv);
+
+    // CBC over the zero padded input, then drop the unused tail of C_{n-1}
+    let remainder = match input.len() % 16 {
+        0 => 16,
+        remainder => remainder,
+    };
+    let mut padded = input;
+    padded.resize(padded.len().div_ceil(16) * 16, 0);
+
+    let mut cipher_text = cbc.encrypt(&padded);
+    if cipher_text.len() > 16 {
+        let stolen_end = cipher_text.len() - 16;
+        cipher_text.drain(stolen_end - (16 - remainder)..stolen_end);
+    }
+
+    // cipher_text is now in CS1 order
+    reorder(&mut cipher_text, variant, remainder, false);
+    cipher_text
+}
+
+pub fn aes_cbc_cts_decrypt(input: Vec<u8>, key: Vec<u8>, iv: Vec<u8>, variant: Cts) -> Result<Vec<u8>, Error> {
+    if input.len() < 16 {
+        return Err(Error::InvalidLength);
+    }
+
+    let mut cbc = Cbc::new(Aes::new(key), iv);
+    if input.len() == 16 {
+        return Ok(cbc.decrypt(&input));
+    }
+
+    let remainder = match input.len() % 16 {
+        0 => 16,
+        remainder => remainder,
+    };
+
+    // put the blocks back into CS1 order
+    let mut cipher_text = input;
+    reorder(&mut cipher_text, variant, remainder, true);
+
+    // C_n decrypts to P_n || 0s xored with C_{n-1}, so its tail
+    // gives back the bytes stolen from C_{n-1}
+    let last = cipher_text.split_off(cipher_text.len() - 16);
+    let stolen_start = cipher_text.len() - remainder;
+    let z = cbc.cipher.decrypt_block(last);
+
+    cipher_text.extend_from_slice(&z[remainder..]);
+    let last_plain = xor(&z[..remainder], &cipher_text[stolen_start..stolen_start + remainder]);
+
+    let mut plain_text = cbc.decrypt(&cipher_text);
+    plain_text.extend_from_slice(&last_plain);
+    Ok(plain_text)
+}
+
+// swap between CS1 order and the order of the variant,
+// the tail is the partial block of remainder bytes and the final full block
+fn reorder(cipher_text: &mut [u8], variant: Cts, remainder: usize, to_cs1: bool) {
+    let swap = match variant {
+        Cts::Cs1 => false,
+        Cts::Cs2 => remainder != 16,
+        Cts::Cs3 => true,
+    };
+    if !swap || cipher_text.len() <= 16 {
+        return;
+    }
+
+    let tail_start = cipher_text.len() - 16 - remainder;
+    let tail = &mut cipher_text[tail_start..];
+    if to_cs1 {
+        tail.rotate_left(16);
+    } else {
+        tail.rotate_left(remainder);
+    }
+}
+
+/* ----------- CBC ------------ */
+// the chaining value carries over between calls
+pub struct Cbc<C: BlockCipher> {
+    cipher: C,
+    previous: Vec<u8>,
+}
+
+impl<C: BlockCipher> Cbc<C> {
+    pub fn new(cipher: C, iv: Vec<u8>) -> Cbc<C> {
+        if iv.len() != cipher.block_size() {
+            panic!("Invalid IV length");
+        }
+
+        Cbc { cipher, previous: iv }
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Cbc<C> {
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut cipher_text: Vec<u8> = Vec::new();
+        for block in input.chunks(self.block_size()) {
+            self.previous = self.cipher.encrypt_block(xor(block, &self.previous));
+            cipher_text.extend_from_slice(&self.previous);
+        }
+        cipher_text
+    }
+
+    fn decrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut plain_text: Vec<u8> = Vec::new();
+        for block in input.chunks(self.block_size()) {
+            let decrypted = self.cipher.decrypt_block(block.to_vec());
+            plain_text.append(&mut xor(&decrypted, &self.previous));
+            self.previous = block.to_vec();
+        }
+        plain_text
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 3962 (Kerberos AES-CTS) inputs, which define CS3
+    const KEY: &[u8] = b"chicken teriyaki";
+    const MESSAGE: &[u8] = b"I would like the General Gau's Chicken, please, and wonton soup.";
+
+    fn check_vector(length: usize, variant: Cts, expected: &str) {
+        let plain_text = MESSAGE[..length].to_vec();
+        let expected = hex::decode(expected).unwrap();
+
+        let encrypted = aes_cbc_cts_encrypt(plain_text.clone(), KEY.to_vec(), vec![0; 16], variant);
+        assert_eq!(encrypted, expected);
+
+        let decrypted = aes_cbc_cts_decrypt(encrypted, KEY.to_vec(), vec![0; 16], variant);
+        assert_eq!(decrypted, Ok(plain_text));
+    }
+
+    #[test]
+    fn cbc_cs3_rfc_3962() {
+        check_vector(17, Cts::Cs3, "c6353568f2bf8cb4d8a580362da7ff7f97");
+        check_vector(31, Cts::Cs3, "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5");
+        check_vector(32, Cts::Cs3, "39312523a78662d5be7fcbcc98ebf5a897687268d6ecccc0c07b25e25ecfe584");
+        check_vector(
+            47,
+            Cts::Cs3,
+            "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e\
+             39312523a78662d5be7fcbcc98ebf5",
+        );
+        check_vector(
+            48,
+            Cts::Cs3,
+            "97687268d6ecccc0c07b25e25ecfe5849dad8bbb96c4cdc03bc103e1a194bbd8\
+             39312523a78662d5be7fcbcc98ebf5a8",
+        );
+        check_vector(
+            64,
+            Cts::Cs3,
+            "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8\
+             4807efe836ee89a526730dbc2f7bc8409dad8bbb96c4cdc03bc103e1a194bbd8",
+        );
+    }
+
+    #[test]
+    fn cbc_cs1() {
+        check_vector(17, Cts::Cs1, "97c6353568f2bf8cb4d8a580362da7ff7f");
+        check_vector(31, Cts::Cs1, "97687268d6ecccc0c07b25e25ecfe5fc00783e0efdb2c1d445d4c8eff7ed22");
+        check_vector(32, Cts::Cs1, "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8");
+        check_vector(
+            47,
+            Cts::Cs1,
+            "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5\
+             b3fffd940c16a18c1b5549d2f838029e",
+        );
+    }
+
+    #[test]
+    fn cbc_cs2() {
+        check_vector(17, Cts::Cs2, "c6353568f2bf8cb4d8a580362da7ff7f97");
+        check_vector(31, Cts::Cs2, "fc00783e0efdb2c1d445d4c8eff7ed2297687268d6ecccc0c07b25e25ecfe5");
+        // aligned input is left as plain CBC
+        check_vector(32, Cts::Cs2, "97687268d6ecccc0c07b25e25ecfe58439312523a78662d5be7fcbcc98ebf5a8");
+        check_vector(
+            47,
+            Cts::Cs2,
+            "97687268d6ecccc0c07b25e25ecfe584b3fffd940c16a18c1b5549d2f838029e\
+             39312523a78662d5be7fcbcc98ebf5",
+        );
+    }
+
+    #[test]
+    fn cts_single_block() {
+        for variant in [Cts::Cs1, Cts::Cs2, Cts::Cs3] {
+            let plain_text = MESSAGE[..16].to_vec();
+            let encrypted = aes_cbc_cts_encrypt(plain_text.clone(), KEY.to_vec(), vec![0; 16], variant);
+
+            assert_eq!(encrypted, Cbc::new(Aes::new(KEY.to_vec()), vec![0; 16]).encrypt(&plain_text));
+            assert_eq!(aes_cbc_cts_decrypt(encrypted, KEY.to_vec(), vec![0; 16], variant), Ok(plain_text));
+        }
+    }
+
+    #[test]
+    fn cts_rejects_short_input() {
+        assert_eq!(
+            aes_cbc_cts_decrypt(vec![0; 15], KEY.to_vec(), vec![0; 16], Cts::Cs3),
+            Err(Error::InvalidLength)
+        );
+    }
+
+    #[test]
+    fn cbc_sp800_38a() {
+        let key = hex::decode("2b7e151628aed2a6abf7158809cf4f3c").unwrap();
+        let iv: Vec<u8> = (0..16).collect();
+        let plain_text = hex::decode(
+            "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+             30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710",
+        )
+        .unwrap();
+        let expected = hex::decode(
+            "7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2\
+             73bed6b8e3c1743b7116e69e222295163ff1caa1681fac09120eca307586e1a7",
+        )
+        .unwrap();
+
+        let encrypted = aes_cbc_encrypt(plain_text.clone(), key.clone(), iv.clone());
+        assert_eq!(encrypted[..64], expected);
+        assert_eq!(aes_cbc_decrypt(encrypted, key, iv), Ok(plain_text));
+    }
+}
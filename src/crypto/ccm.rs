@@ -0,0 +1,255 @@
+use super::block::{Aes, BlockCipher};
+use super::{constant_time_eq, xor, Error};
+
+/* ----------- ENCRYPTION AND DECRYPTION ------------ */
+// returns the ciphertext followed by a tag of tag_length bytes
+pub fn aes_ccm_encrypt(
+    input: Vec<u8>,
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    associated_data: Vec<u8>,
+    tag_length: usize,
+) -> Result<Vec<u8>, Error> {
+    let ccm = Ccm::new(key, nonce, tag_length);
+
+    if !ccm.fits(input.len()) {
+        return Err(Error::InvalidLength);
+    }
+
+    let tag = ccm.mac(&input, &associated_data);
+    let mut cipher_text = ccm.ctr(&input);
+    cipher_text.append(&mut ccm.encrypt_tag(&tag));
+    Ok(cipher_text)
+}
+
+pub fn aes_ccm_decrypt(
+    input: Vec<u8>,
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    associated_data: Vec<u8>,
+    tag_length: usize,
+) -> Result<Vec<u8>, Error> {
+    let ccm = Ccm::new(key, nonce, tag_length);
+
+    if input.len() < tag_length || !ccm.fits(input.len() - tag_length) {
+        return Err(Error::InvalidLength);
+    }
+    let (cipher_text, received_tag) = input.split_at(input.len() - tag_length);
+
+    let plain_text = ccm.ctr(cipher_text);
+    let tag = ccm.encrypt_tag(&ccm.mac(&plain_text, &associated_data));
+
+    if !constant_time_eq(&tag, received_tag) {
+        return Err(Error::AuthenticationFailed);
+    }
+
+    Ok(plain_text)
+}
+
+/* ----------- CCM FUNCTIONS ------------ */
+struct Ccm {
+    cipher: Aes,
+    nonce: Vec<u8>,
+    tag_length: usize,
+    // size of the message length field, 15 - nonce length
+    length_size: usize,
+}
+
+impl Ccm {
+    fn new(key: Vec<u8>, nonce: Vec<u8>, tag_length: usize) -> Ccm {
+        if !(7..=13).contains(&nonce.len()) {
+            panic!("Invalid nonce length");
+        }
+        if !(4..=16).contains(&tag_length) || !tag_length.is_multiple_of(2) {
+            panic!("Invalid tag length");
+        }
+
+        let length_size = 15 - nonce.len();
+
+        Ccm {
+            cipher: Aes::new(key),
+            nonce,
+            tag_length,
+            length_size,
+        }
+    }
+
+    fn encrypt_block(&self, block: Vec<u8>) -> Vec<u8> {
+        self.cipher.encrypt_block(block)
+    }
+
+    // the message length has to fit in length_size bytes
+    fn fits(&self, length: usize) -> bool {
+        self.length_size >= 8 || length >> (8 * self.length_size) == 0
+    }
+
+    // flags || nonce || value, with value taking up the last length_size bytes
+    fn format_block(&self, flags: u8, value: usize) -> Vec<u8> {
+        let mut block = vec![flags];
+        block.extend_from_slice(&self.nonce);
+        block.extend_from_slice(&(value as u128).to_be_bytes()[16 - self.length_size..]);
+        block
+    }
+
+    // CBC-MAC over B_0, the encoded associated data and the message
+    fn mac(&self, message: &[u8], associated_data: &[u8]) -> Vec<u8> {
+        let adata = if associated_data.is_empty() { 0 } else { 0x40 };
+        let flags = adata | (((self.tag_length - 2) / 2) as u8) << 3 | (self.length_size - 1) as u8;
+
+        let mut blocks = self.format_block(flags, message.len());
+
+        if !associated_data.is_empty() {
+            let length = associated_data.len() as u64;
+            if length < 0xff00 {
+                blocks.extend_from_slice(&(length as u16).to_be_bytes());
+            } else if length <= u32::MAX as u64 {
+                blocks.extend_from_slice(&[0xff, 0xfe]);
+                blocks.extend_from_slice(&(length as u32).to_be_bytes());
+            } else {
+                blocks.extend_from_slice(&[0xff, 0xff]);
+                blocks.extend_from_slice(&length.to_be_bytes());
+            }
+            blocks.extend_from_slice(associated_data);
+            blocks.resize(blocks.len().div_ceil(16) * 16, 0);
+        }
+
+        blocks.extend_from_slice(message);
+        blocks.resize(blocks.len().div_ceil(16) * 16, 0);
+
+        let mut x = vec![0; 16];
+        for block in blocks.chunks(16) {
+            x = self.encrypt_block(xor(&x, block));
+        }
+
+        x.truncate(self.tag_length);
+        x
+    }
+
+    // counter blocks A_1, A_2, ... encrypt the message
+    fn ctr(&self, input: &[u8]) -> Vec<u8> {
+        let flags = (self.length_size - 1) as u8;
+
+        let mut output: Vec<u8> = Vec::new();
+        for (i, chunk) in input.chunks(16).enumerate() {
+            let keystream = self.encrypt_block(self.format_block(flags, i + 1));
+            output.append(&mut xor(chunk, &keystream));
+        }
+        output
+    }
+
+    // counter block A_0 encrypts the tag
+    fn encrypt_tag(&self, tag: &[u8]) -> Vec<u8> {
+        let flags = (self.length_size - 1) as u8;
+        let keystream = self.encrypt_block(self.format_block(flags, 0));
+        xor(tag, &keystream)
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 3610 packet vectors, the first header_length bytes of the packet
+    // are authenticated and the rest is encrypted
+    fn check_vector(number: u8, packet_length: u8, header_length: usize, tag_length: usize, expected: &str) {
+        let key: Vec<u8> = (0xc0..=0xcf).collect();
+        let mut nonce = vec![0x00, 0x00, 0x00, number + 2, number + 1, number, number - 1];
+        nonce.extend_from_slice(&[0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5]);
+
+        let packet: Vec<u8> = (0..packet_length).collect();
+        let (header, payload) = packet.split_at(header_length);
+        let expected = hex::decode(expected).unwrap();
+
+        let encrypted = aes_ccm_encrypt(payload.to_vec(), key.clone(), nonce.clone(), header.to_vec(), tag_length).unwrap();
+        assert_eq!(encrypted, expected);
+
+        let decrypted = aes_ccm_decrypt(encrypted, key, nonce, header.to_vec(), tag_length);
+        assert_eq!(decrypted, Ok(payload.to_vec()));
+    }
+
+    #[test]
+    fn rfc_3610_packet_vector_1() {
+        check_vector(1, 31, 8, 8, "588c979a61c663d2f066d0c2c0f989806d5f6b61dac38417e8d12cfdf926e0");
+    }
+
+    #[test]
+    fn rfc_3610_packet_vector_2() {
+        check_vector(2, 32, 8, 8, "72c91a36e135f8cf291ca894085c87e3cc15c439c9e43a3ba091d56e10400916");
+    }
+
+    #[test]
+    fn rfc_3610_packet_vector_3() {
+        check_vector(3, 33, 8, 8, "51b1e5f44a197d1da46b0f8e2d282ae871e838bb64da8596574adaa76fbd9fb0c5");
+    }
+
+    #[test]
+    fn rfc_3610_packet_vector_4() {
+        check_vector(4, 31, 12, 8, "a28c6865939a9a79faaa5c4c2a9d4a91cdac8c96c861b9c9e61ef1");
+    }
+
+    #[test]
+    fn rfc_3610_packet_vector_7() {
+        check_vector(7, 31, 8, 10, "0135d1b2c95f41d5d1d4fec185d166b8094e999dfed96c048c56602c97acbb7490");
+    }
+
+    #[test]
+    fn rfc_3610_packet_vector_9() {
+        check_vector(9, 33, 8, 10, "82531a60cc24945a4b8279181ab5c84df21ce7f9b73f42e197ea9c07e56b5eb17e5f4e");
+    }
+
+    #[test]
+    fn short_nonce_round_trip() {
+        let key = crate::key_gen(256);
+        let nonce = vec![0x42; 7];
+        let message = b"partner device payload".to_vec();
+
+        let encrypted = aes_ccm_encrypt(message.clone(), key.clone(), nonce.clone(), Vec::new(), 4).unwrap();
+        assert_eq!(encrypted.len(), message.len() + 4);
+
+        let decrypted = aes_ccm_decrypt(encrypted, key, nonce, Vec::new(), 4);
+        assert_eq!(decrypted, Ok(message));
+    }
+
+    #[test]
+    fn decrypt_rejects_tampered() {
+        let key = crate::key_gen(128);
+        let nonce = vec![0x01; 13];
+        let header = b"header".to_vec();
+
+        let encrypted = aes_ccm_encrypt(b"message".to_vec(), key.clone(), nonce.clone(), header.clone(), 16).unwrap();
+
+        let mut tampered = encrypted.clone();
+        tampered[0] ^= 0x01;
+        assert_eq!(
+            aes_ccm_decrypt(tampered, key.clone(), nonce.clone(), header, 16),
+            Err(Error::AuthenticationFailed)
+        );
+
+        assert_eq!(
+            aes_ccm_decrypt(encrypted, key, nonce, b"Header".to_vec(), 16),
+            Err(Error::AuthenticationFailed)
+        );
+    }
+
+    #[test]
+    fn rejects_messages_too_long_for_the_nonce() {
+        let key = crate::key_gen(128);
+        // a 13 byte nonce leaves 2 bytes for the length, so at most 64 KiB - 1
+        let nonce = vec![0x01; 13];
+
+        let fits = aes_ccm_encrypt(vec![0; 0xffff], key.clone(), nonce.clone(), Vec::new(), 16);
+        assert!(fits.is_ok());
+
+        assert_eq!(
+            aes_ccm_encrypt(vec![0; 0x10000], key.clone(), nonce.clone(), Vec::new(), 16),
+            Err(Error::InvalidLength)
+        );
+        assert_eq!(
+            aes_ccm_decrypt(vec![0; 0x10000 + 16], key, nonce, Vec::new(), 16),
+            Err(Error::InvalidLength)
+        );
+    }
+}
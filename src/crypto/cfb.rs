@@ -0,0 +1,190 @@
+use super::block::{Aes, BlockCipher};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Bits1,
+    Bits8,
+    Bits128,
+}
+
+/* ----------- ONE SHOT ------------ */
+pub fn aes_cfb_encrypt(input: Vec<u8>, key: Vec<u8>, iv: Vec<u8>, segment: Segment) -> Vec<u8> {
+    Cfb::new(key, iv, segment).encrypt(&input)
+}
+
+pub fn aes_cfb_decrypt(input: Vec<u8>, key: Vec<u8>, iv: Vec<u8>, segment: Segment) -> Vec<u8> {
+    Cfb::new(key, iv, segment).decrypt(&input)
+}
+
+/* ----------- STREAMING ------------ */
+// keeps the shift register between calls so input can be fed in pieces
+pub struct Cfb {
+    cipher: Aes,
+    segment: Segment,
+    register: Vec<u8>,
+    keystream: Vec<u8>,
+    // byte position inside the current block for 128 bit segments
+    position: usize,
+}
+
+impl Cfb {
+    pub fn new(key: Vec<u8>, iv: Vec<u8>, segment: Segment) -> Cfb {
+        if iv.len() != 16 {
+            panic!("Invalid IV length");
+        }
+
+        Cfb {
+            cipher: Aes::new(key),
+            segment,
+            register: iv,
+            keystream: Vec::new(),
+            position: 0,
+        }
+    }
+
+    pub fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        input.iter().map(|&byte| self.process_byte(byte, true)).collect()
+    }
+
+    pub fn decrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        input.iter().map(|&byte| self.process_byte(byte, false)).collect()
+    }
+
+    fn process_byte(&mut self, byte: u8, encrypting: bool) -> u8 {
+        match self.segment {
+            Segment::Bits1 => {
+                let mut output = 0;
+                for bit in (0..8).rev() {
+                    let keystream = self.cipher.encrypt_block(self.register.clone());
+
+                    let input_bit = (byte >> bit) & 1;
+                    let output_bit = input_bit ^ (keystream[0] >> 7);
+                    output |= output_bit << bit;
+
+                    let feedback = if encrypting { output_bit } else { input_bit };
+                    shift_in_bit(&mut self.register, feedback);
+                }
+                output
+            }
+            Segment::Bits8 => {
+                let keystream = self.cipher.encrypt_block(self.register.clone());
+                let output = byte ^ keystream[0];
+
+                self.register.remove(0);
+                self.register.push(if encrypting { output } else { byte });
+                output
+            }
+            Segment::Bits128 => {
+                if self.position == 0 {
+                    self.keystream = self.cipher.encrypt_block(self.register.clone());
+                }
+                let output = byte ^ self.keystream[self.position];
+
+                // the register fills up with the ciphertext block as it is produced
+                self.register[self.position] = if encrypting { output } else { byte };
+                self.position = (self.position + 1) % 16;
+                output
+            }
+        }
+    }
+}
+
+// shift the whole register left by one bit, appending the new bit at the end
+fn shift_in_bit(register: &mut [u8], bit: u8) {
+    let mut carry = bit;
+    for byte in register.iter_mut().rev() {
+        let next_carry = *byte >> 7;
+        *byte = (*byte << 1) | carry;
+        carry = next_carry;
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY_128: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const KEY_192: &str = "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b";
+    const KEY_256: &str = "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4";
+    const PLAIN_TEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                              30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    fn check_vector(key: &str, segment: Segment, length: usize, expected: &str) {
+        let key = hex::decode(key).unwrap();
+        let iv: Vec<u8> = (0..16).collect();
+        let plain_text = hex::decode(PLAIN_TEXT).unwrap()[..length].to_vec();
+        let expected = hex::decode(expected).unwrap();
+
+        let encrypted = aes_cfb_encrypt(plain_text.clone(), key.clone(), iv.clone(), segment);
+        assert_eq!(encrypted, expected);
+
+        let decrypted = aes_cfb_decrypt(encrypted, key, iv, segment);
+        assert_eq!(decrypted, plain_text);
+    }
+
+    #[test]
+    fn cfb1_sp800_38a() {
+        check_vector(KEY_128, Segment::Bits1, 2, "68b3");
+        check_vector(KEY_192, Segment::Bits1, 2, "9359");
+        check_vector(KEY_256, Segment::Bits1, 2, "9029");
+    }
+
+    #[test]
+    fn cfb8_sp800_38a() {
+        check_vector(KEY_128, Segment::Bits8, 18, "3b79424c9c0dd436bace9e0ed4586a4f32b9");
+        check_vector(KEY_192, Segment::Bits8, 18, "cda2521ef0a905ca44cd057cbf0d47a0678a");
+        check_vector(KEY_256, Segment::Bits8, 18, "dc1f1a8520a64db55fcc8ac554844e889700");
+    }
+
+    #[test]
+    fn cfb128_sp800_38a() {
+        check_vector(
+            KEY_128,
+            Segment::Bits128,
+            64,
+            "3b3fd92eb72dad20333449f8e83cfb4ac8a64537a0b3a93fcde3cdad9f1ce58b\
+             26751f67a3cbb140b1808cf187a4f4dfc04b05357c5d1c0eeac4c66f9ff7f2e6",
+        );
+        check_vector(
+            KEY_192,
+            Segment::Bits128,
+            64,
+            "cdc80d6fddf18cab34c25909c99a417467ce7f7f81173621961a2b70171d3d7a\
+             2e1e8a1dd59b88b1c8e60fed1efac4c9c05f9f9ca9834fa042ae8fba584b09ff",
+        );
+        check_vector(
+            KEY_256,
+            Segment::Bits128,
+            64,
+            "dc7e84bfda79164b7ecd8486985d386039ffed143b28b1c832113c6331e5407b\
+             df10132415e54b92a13ed0a8267ae2f975a385741ab9cef82031623d55b1e471",
+        );
+    }
+
+    #[test]
+    fn cfb128_streaming() {
+        let key = hex::decode(KEY_128).unwrap();
+        let iv: Vec<u8> = (0..16).collect();
+        let plain_text = hex::decode(PLAIN_TEXT).unwrap();
+
+        let expected = aes_cfb_encrypt(plain_text.clone(), key.clone(), iv.clone(), Segment::Bits128);
+
+        // feed pieces that don't line up with the block boundaries
+        let mut cfb = Cfb::new(key.clone(), iv.clone(), Segment::Bits128);
+        let mut encrypted: Vec<u8> = Vec::new();
+        for piece in plain_text.chunks(7) {
+            encrypted.append(&mut cfb.encrypt(piece));
+        }
+        assert_eq!(encrypted, expected);
+
+        let mut cfb = Cfb::new(key, iv, Segment::Bits128);
+        let mut decrypted: Vec<u8> = Vec::new();
+        for piece in encrypted.chunks(20) {
+            decrypted.append(&mut cfb.decrypt(piece));
+        }
+        assert_eq!(decrypted, plain_text);
+    }
+}
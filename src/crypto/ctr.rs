@@ -0,0 +1,184 @@
+use super::block::{Aes, BlockCipher, BlockMode};
+use super::nonce::NonceTracker;
+use super::Error;
+
+/* ----------- ONE SHOT ------------ */
+// counter is the initial counter block, incremented as a big endian number
+pub fn aes_ctr_encrypt(input: Vec<u8>, key: Vec<u8>, counter: Vec<u8>) -> Vec<u8> {
+    Ctr::new(Aes::new(key), counter).apply_keystream(&input)
+}
+
+// refuses to encrypt if any of the counter blocks the message needs has already
+// been used with this key
+pub fn aes_ctr_encrypt_tracked(
+    input: Vec<u8>,
+    key: Vec<u8>,
+    counter: Vec<u8>,
+    tracker: &mut NonceTracker,
+) -> Result<Vec<u8>, Error> {
+    if counter.len() != 16 {
+        panic!("Invalid counter length");
+    }
+
+    let mut first = [0; 16];
+    first.copy_from_slice(&counter);
+    let blocks = input.len().div_ceil(16) as u128;
+    tracker.record_counters(&key, u128::from_be_bytes(first), blocks)?;
+    Ok(aes_ctr_encrypt(input, key, counter))
+}
+
+// CTR decryption is the same operation as encryption
+pub fn aes_ctr_decrypt(input: Vec<u8>, key: Vec<u8>, counter: Vec<u8>) -> Vec<u8> {
+    Ctr::new(Aes::new(key), counter).apply_keystream(&input)
+}
+
+/* ----------- CTR ------------ */
+// keeps the counter and unused keystream between calls
+pub struct Ctr<C: BlockCipher> {
+    cipher: C,
+    counter: Vec<u8>,
+    keystream: Vec<u8>,
+    position: usize,
+}
+
+impl<C: BlockCipher> Ctr<C> {
+    pub fn new(cipher: C, counter: Vec<u8>) -> Ctr<C> {
+        if counter.len() != cipher.block_size() {
+            panic!("Invalid counter length");
+        }
+
+        let position = cipher.block_size();
+        Ctr {
+            cipher,
+            counter,
+            keystream: Vec::new(),
+            position,
+        }
+    }
+
+    pub fn apply_keystream(&mut self, input: &[u8]) -> Vec<u8> {
+        let block_size = self.cipher.block_size();
+
+        let mut output: Vec<u8> = Vec::new();
+        for byte in input {
+            if self.position == block_size {
+                self.keystream = self.cipher.encrypt_block(self.counter.clone());
+                increment(&mut self.counter);
+                self.position = 0;
+            }
+            output.push(byte ^ self.keystream[self.position]);
+            self.position += 1;
+        }
+        output
+    }
+}
+
+impl<C: BlockCipher> BlockMode for Ctr<C> {
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn encrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        self.apply_keystream(input)
+    }
+
+    fn decrypt(&mut self, input: &[u8]) -> Vec<u8> {
+        self.apply_keystream(input)
+    }
+}
+
+// add one to the whole block, wrapping around at the top
+fn increment(counter: &mut [u8]) {
+    for byte in counter.iter_mut().rev() {
+        *byte = byte.wrapping_add(1);
+        if *byte != 0 {
+            break;
+        }
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN_TEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                              30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+    const COUNTER: &str = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff";
+
+    fn check_vector(key: &str, expected: &str) {
+        let key = hex::decode(key).unwrap();
+        let counter = hex::decode(COUNTER).unwrap();
+        let plain_text = hex::decode(PLAIN_TEXT).unwrap();
+        let expected = hex::decode(expected).unwrap();
+
+        let encrypted = aes_ctr_encrypt(plain_text.clone(), key.clone(), counter.clone());
+        assert_eq!(encrypted, expected);
+        assert_eq!(aes_ctr_decrypt(encrypted, key, counter), plain_text);
+    }
+
+    #[test]
+    fn ctr_128_sp800_38a() {
+        check_vector(
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "874d6191b620e3261bef6864990db6ce9806f66b7970fdff8617187bb9fffdff\
+             5ae4df3edbd5d35e5b4f09020db03eab1e031dda2fbe03d1792170a0f3009cee",
+        );
+    }
+
+    #[test]
+    fn ctr_256_sp800_38a() {
+        check_vector(
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+            "601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5\
+             2b0930daa23de94ce87017ba2d84988ddfc9c58db67aada613c2dd08457941a6",
+        );
+    }
+
+    #[test]
+    fn tracked_encryption_refuses_reuse() {
+        let mut tracker = NonceTracker::new();
+        let key = crate::key_gen(128);
+        let counter = crate::iv_gen();
+
+        assert!(aes_ctr_encrypt_tracked(vec![1; 16], key.clone(), counter.clone(), &mut tracker).is_ok());
+        assert_eq!(
+            aes_ctr_encrypt_tracked(vec![2; 16], key, counter, &mut tracker),
+            Err(Error::NonceReused)
+        );
+    }
+
+    #[test]
+    fn tracked_encryption_refuses_overlapping_counters() {
+        let mut tracker = NonceTracker::new();
+        let key = crate::key_gen(128);
+        let counter = vec![0; 16];
+        let mut next = counter.clone();
+        increment(&mut next);
+
+        // two blocks at N, then a message starting at N + 1
+        assert!(aes_ctr_encrypt_tracked(vec![1; 32], key.clone(), counter, &mut tracker).is_ok());
+        assert_eq!(
+            aes_ctr_encrypt_tracked(vec![2; 16], key.clone(), next.clone(), &mut tracker),
+            Err(Error::NonceReused)
+        );
+
+        increment(&mut next);
+        assert!(aes_ctr_encrypt_tracked(vec![3; 16], key, next, &mut tracker).is_ok());
+    }
+
+    #[test]
+    fn counter_wraps() {
+        let mut counter = vec![0xff; 16];
+        increment(&mut counter);
+        assert_eq!(counter, vec![0; 16]);
+
+        let encrypted = aes_ctr_encrypt(vec![0; 32], vec![0; 16], vec![0xff; 16]);
+        assert_eq!(
+            encrypted,
+            hex::decode("3f5b8cc9ea855a0afa7347d23e8d664e66e94bd4ef8a2c3b884cfa59ca342b2e").unwrap()
+        );
+    }
+}
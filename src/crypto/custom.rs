@@ -0,0 +1,96 @@
+use super::aes::{self, Components};
+use super::block::BlockCipher;
+
+// AES with the S-box and the MixColumns matrix swapped out, for trying
+// alternatives. The key expansion is left as AES has it, the tables come in
+// an aes::Components.
+
+#[derive(Clone)]
+pub struct CustomAes {
+    num_rounds: u8,
+    key_schedule: Vec<Vec<u8>>,
+    components: Components,
+}
+
+impl CustomAes {
+    pub fn new(key: Vec<u8>, components: Components) -> CustomAes {
+        if ![16, 24, 32].contains(&key.len()) {
+            panic!("Invalid key length");
+        }
+
+        CustomAes {
+            num_rounds: aes::num_rounds(key.len() / 4),
+            key_schedule: aes::key_expansion(key),
+            components,
+        }
+    }
+}
+
+impl BlockCipher for CustomAes {
+    fn block_size(&self) -> usize {
+        16
+    }
+
+    fn encrypt_block(&self, block: Vec<u8>) -> Vec<u8> {
+        if block.len() != 16 {
+            panic!("Invalid block length");
+        }
+        aes::custom_cipher(block, self.num_rounds, &self.key_schedule, &self.components)
+    }
+
+    fn decrypt_block(&self, block: Vec<u8>) -> Vec<u8> {
+        if block.len() != 16 {
+            panic!("Invalid block length");
+        }
+        aes::custom_inv_cipher(block, self.num_rounds, &self.key_schedule, &self.components)
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::block::Aes;
+
+    #[test]
+    fn aes_components_are_aes() {
+        // the tables match what the S-box derivation and matrix inversion give
+        let components = Components::aes();
+        assert_eq!(Components::new(crate::crypto::sbox::generate_s_box(), aes::MIX_COLUMNS), components);
+
+        let key = crate::key_gen(192);
+        let input = crate::iv_gen();
+        let custom = CustomAes::new(key.clone(), components);
+        let expected = Aes::new(key).encrypt_block(input.clone());
+        assert_eq!(custom.encrypt_block(input.clone()), expected);
+        assert_eq!(custom.decrypt_block(expected), input);
+    }
+
+    #[test]
+    fn round_trip_other_components() {
+        // an affine S-box and a circulant matrix, both terrible but invertible
+        let mut s_box = [0; 256];
+        for (i, entry) in s_box.iter_mut().enumerate() {
+            *entry = (i as u8).wrapping_mul(5).wrapping_add(7);
+        }
+        let components = Components::new(s_box, [[1, 1, 0, 0], [0, 1, 1, 0], [0, 0, 1, 1], [1, 0, 0, 2]]);
+
+        let custom = CustomAes::new(crate::key_gen(128), components);
+        let input = crate::iv_gen();
+        assert_eq!(custom.decrypt_block(custom.encrypt_block(input.clone())), input);
+    }
+
+    #[test]
+    #[should_panic(expected = "S-box is not a permutation")]
+    fn rejects_non_permutation() {
+        Components::new([0; 256], aes::MIX_COLUMNS);
+    }
+
+    #[test]
+    #[should_panic(expected = "MixColumns matrix is not invertible")]
+    fn rejects_singular_matrix() {
+        Components::new(Components::aes().s_box, [[1, 1, 1, 1]; 4]);
+    }
+}
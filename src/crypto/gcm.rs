@@ -0,0 +1,276 @@
+use super::block::{Aes, BlockCipher};
+use super::nonce::NonceTracker;
+use super::{constant_time_eq, xor, Error};
+
+/* ----------- ENCRYPTION AND DECRYPTION ------------ */
+// SP 800-38D, returns the ciphertext followed by a tag of tag_length bytes
+pub fn aes_gcm_encrypt(
+    input: Vec<u8>,
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    associated_data: Vec<u8>,
+    tag_length: usize,
+) -> Vec<u8> {
+    let gcm = Gcm::new(key, tag_length);
+    let counter = gcm.initial_counter(&nonce);
+
+    let mut cipher_text = gcm.ctr(&input, counter);
+    let mut tag = gcm.tag(&associated_data, &cipher_text, counter);
+    cipher_text.append(&mut tag);
+    cipher_text
+}
+
+// refuses to encrypt if the nonce has already been used with this key
+pub fn aes_gcm_encrypt_tracked(
+    input: Vec<u8>,
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    associated_data: Vec<u8>,
+    tag_length: usize,
+    tracker: &mut NonceTracker,
+) -> Result<Vec<u8>, Error> {
+    tracker.record(&key, &nonce)?;
+    Ok(aes_gcm_encrypt(input, key, nonce, associated_data, tag_length))
+}
+
+pub fn aes_gcm_decrypt(
+    input: Vec<u8>,
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    associated_data: Vec<u8>,
+    tag_length: usize,
+) -> Result<Vec<u8>, Error> {
+    let gcm = Gcm::new(key, tag_length);
+    let counter = gcm.initial_counter(&nonce);
+
+    if input.len() < tag_length {
+        return Err(Error::InvalidLength);
+    }
+    let (cipher_text, received_tag) = input.split_at(input.len() - tag_length);
+
+    let tag = gcm.tag(&associated_data, cipher_text, counter);
+    if !constant_time_eq(&tag, received_tag) {
+        return Err(Error::AuthenticationFailed);
+    }
+
+    Ok(gcm.ctr(cipher_text, counter))
+}
+
+/* ----------- GCM FUNCTIONS ------------ */
+struct Gcm {
+    cipher: Aes,
+    tag_length: usize,
+    // the hash key, E(K, 0^128)
+    h: u128,
+}
+
+impl Gcm {
+    fn new(key: Vec<u8>, tag_length: usize) -> Gcm {
+        if ![4, 8, 12, 13, 14, 15, 16].contains(&tag_length) {
+            panic!("Invalid tag length");
+        }
+
+        let cipher = Aes::new(key);
+        let h = from_block(&cipher.encrypt_block(vec![0; 16]));
+
+        Gcm {
+            cipher,
+            tag_length,
+            h,
+        }
+    }
+
+    fn encrypt_block(&self, block: u128) -> u128 {
+        from_block(&self.cipher.encrypt_block(to_block(block)))
+    }
+
+    // J_0, 96 bit nonces are used directly and anything else is hashed
+    fn initial_counter(&self, nonce: &[u8]) -> u128 {
+        if nonce.is_empty() {
+            panic!("Invalid nonce length");
+        }
+        if nonce.len() == 12 {
+            return pad_partial(nonce) | 1;
+        }
+        // the length block ghash appends is 0^64 || len(nonce), as J_0 needs
+        ghash(self.h, &[], nonce)
+    }
+
+    fn ctr(&self, input: &[u8], initial_counter: u128) -> Vec<u8> {
+        let mut counter = initial_counter;
+        let mut output: Vec<u8> = Vec::new();
+
+        for chunk in input.chunks(16) {
+            counter = increment(counter);
+            let keystream = to_block(self.encrypt_block(counter));
+            output.append(&mut xor(chunk, &keystream));
+        }
+        output
+    }
+
+    fn tag(&self, associated_data: &[u8], cipher_text: &[u8], initial_counter: u128) -> Vec<u8> {
+        let s = ghash(self.h, associated_data, cipher_text);
+        let tag = to_block(s ^ self.encrypt_block(initial_counter));
+        tag[..self.tag_length].to_vec()
+    }
+}
+
+// GHASH over the padded associated data, padded ciphertext and the length block
+pub(crate) fn ghash(h: u128, associated_data: &[u8], cipher_text: &[u8]) -> u128 {
+    let mut y: u128 = 0;
+    for block in associated_data.chunks(16).chain(cipher_text.chunks(16)) {
+        y = gf_mul(y ^ pad_partial(block), h);
+    }
+    let lengths = ((associated_data.len() as u128 * 8) << 64) | (cipher_text.len() as u128 * 8);
+    gf_mul(y ^ lengths, h)
+}
+
+// multiplication in GF(2^128) with the bit reflected GCM convention,
+// the most significant bit is the coefficient of x^0
+pub(crate) fn gf_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+
+    let mut z: u128 = 0;
+    let mut v = y;
+    for i in (0..128).rev() {
+        if (x >> i) & 1 == 1 {
+            z ^= v;
+        }
+        v = if v & 1 == 1 { (v >> 1) ^ R } else { v >> 1 };
+    }
+    z
+}
+
+// only the low 32 bits count, wrapping without carrying into the nonce
+fn increment(counter: u128) -> u128 {
+    let low = (counter as u32).wrapping_add(1);
+    (counter >> 32 << 32) | low as u128
+}
+
+pub(crate) fn pad_partial(bytes: &[u8]) -> u128 {
+    let mut block = bytes.to_vec();
+    block.resize(16, 0);
+    from_block(&block)
+}
+
+pub(crate) fn from_block(block: &[u8]) -> u128 {
+    u128::from_be_bytes(block.try_into().unwrap())
+}
+
+pub(crate) fn to_block(block: u128) -> Vec<u8> {
+    block.to_be_bytes().to_vec()
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "feffe9928665731c6d6a8f9467308308";
+    const PLAIN_TEXT: &str = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a72\
+                              1c3c0c95956809532fcf0e2449a6b525b16aedf5aa0de657ba637b39";
+    const ASSOCIATED_DATA: &str = "feedfacedeadbeeffeedfacedeadbeefabaddad2";
+
+    fn check_vector(key: &str, nonce: &str, plain_text: &str, associated_data: &str, expected: &str) {
+        let key = hex::decode(key).unwrap();
+        let nonce = hex::decode(nonce).unwrap();
+        let plain_text = hex::decode(plain_text).unwrap();
+        let associated_data = hex::decode(associated_data).unwrap();
+        let expected = hex::decode(expected).unwrap();
+
+        let encrypted = aes_gcm_encrypt(
+            plain_text.clone(),
+            key.clone(),
+            nonce.clone(),
+            associated_data.clone(),
+            16,
+        );
+        assert_eq!(encrypted, expected);
+        assert_eq!(aes_gcm_decrypt(encrypted, key, nonce, associated_data, 16), Ok(plain_text));
+    }
+
+    // test cases from the original GCM specification
+    #[test]
+    fn zero_key() {
+        let key = "00000000000000000000000000000000";
+        let nonce = "000000000000000000000000";
+        check_vector(key, nonce, "", "", "58e2fccefa7e3061367f1d57a4e7455a");
+        check_vector(
+            key,
+            nonce,
+            "00000000000000000000000000000000",
+            "",
+            "0388dace60b6a392f328c2b971b2fe78ab6e47d42cec13bdf53a67b21257bddf",
+        );
+    }
+
+    #[test]
+    fn test_case_4() {
+        check_vector(
+            KEY,
+            "cafebabefacedbaddecaf888",
+            PLAIN_TEXT,
+            ASSOCIATED_DATA,
+            "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e\
+             21d514b25466931c7d8f6a5aac84aa051ba30b396a0aac973d58e091\
+             5bc94fbc3221a5db94fae95ae7121a47",
+        );
+    }
+
+    #[test]
+    fn test_case_6_long_nonce() {
+        check_vector(
+            KEY,
+            "9313225df88406e555909c5aff5269aa6a7a9538534f7da1e4c303d2a318a728\
+             c3c0c95156809539fcf0e2429a6b525416aedbf5a0de6a57a637b39b",
+            PLAIN_TEXT,
+            ASSOCIATED_DATA,
+            "8ce24998625615b603a033aca13fb894be9112a5c3a211a8ba262a3cca7e2ca7\
+             01e4a9a4fba43c90ccdcb281d48c7c6fd62875d2aca417034c34aee5\
+             619cc5aefffe0bfa462af43c1699d050",
+        );
+    }
+
+    #[test]
+    fn test_case_16_aes_256() {
+        check_vector(
+            "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+            "cafebabefacedbaddecaf888",
+            PLAIN_TEXT,
+            ASSOCIATED_DATA,
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa\
+             8cb08e48590dbb3da7b08b1056828838c5f61e6393ba7a0abcc9f662\
+             76fc6ece0f4e1768cddf8853bb2d551b",
+        );
+    }
+
+    #[test]
+    fn rejects_tampering() {
+        let key = crate::key_gen(128);
+        let nonce = crate::iv_gen()[..12].to_vec();
+        let mut encrypted = aes_gcm_encrypt(b"hello".to_vec(), key.clone(), nonce.clone(), Vec::new(), 12);
+
+        encrypted[0] ^= 1;
+        assert_eq!(
+            aes_gcm_decrypt(encrypted.clone(), key.clone(), nonce.clone(), Vec::new(), 12),
+            Err(Error::AuthenticationFailed)
+        );
+        assert_eq!(aes_gcm_decrypt(vec![0; 4], key, nonce, Vec::new(), 12), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn tracked_encryption_refuses_reuse() {
+        let mut tracker = NonceTracker::new();
+        let key = crate::key_gen(128);
+        let nonce = vec![7; 12];
+
+        assert!(aes_gcm_encrypt_tracked(vec![1], key.clone(), nonce.clone(), Vec::new(), 16, &mut tracker).is_ok());
+        assert_eq!(
+            aes_gcm_encrypt_tracked(vec![2], key.clone(), nonce, Vec::new(), 16, &mut tracker),
+            Err(Error::NonceReused)
+        );
+        assert!(aes_gcm_encrypt_tracked(vec![3], key, vec![8; 12], Vec::new(), 16, &mut tracker).is_ok());
+    }
+}
@@ -0,0 +1,460 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Sub};
+
+// An element of GF(2^8), a byte read as a polynomial over GF(2) with bit i
+// the coefficient of x^i. Addition is xor, multiplication is polynomial
+// multiplication reduced modulo an irreducible polynomial of degree 8.
+//
+// AES uses x^8 + x^4 + x^3 + x + 1 but any irreducible polynomial gives a
+// field, with_polynomial builds elements of those.
+
+// x^8 + x^4 + x^3 + x + 1
+pub const AES_POLYNOMIAL: u16 = 0x11b;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Gf256 {
+    value: u8,
+    polynomial: u16,
+}
+
+impl Gf256 {
+    pub fn new(value: u8) -> Gf256 {
+        Gf256 {
+            value,
+            polynomial: AES_POLYNOMIAL,
+        }
+    }
+
+    pub fn with_polynomial(value: u8, polynomial: u16) -> Gf256 {
+        if !is_irreducible(polynomial) {
+            panic!("Polynomial is not irreducible");
+        }
+        Gf256 { value, polynomial }
+    }
+
+    pub fn value(&self) -> u8 {
+        self.value
+    }
+
+    pub fn polynomial(&self) -> u16 {
+        self.polynomial
+    }
+
+    pub fn zero(&self) -> Gf256 {
+        Gf256 { value: 0, ..*self }
+    }
+
+    pub fn one(&self) -> Gf256 {
+        Gf256 { value: 1, ..*self }
+    }
+
+    // square and multiply, x^0 = 1 including 0^0
+    pub fn pow(self, mut exponent: u32) -> Gf256 {
+        let mut result = self.one();
+        let mut base = self;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result *= base;
+            }
+            base *= base;
+            exponent >>= 1;
+        }
+        result
+    }
+
+    // x^254 = x^-1 as the nonzero elements form a group of order 255, and
+    // 0 maps to 0 as in the AES S-box
+    pub fn inverse(self) -> Gf256 {
+        self.pow(254)
+    }
+
+    // the element written out as a polynomial, like x^7 + x + 1
+    pub fn as_polynomial(&self) -> String {
+        polynomial_string(self.value as u16)
+    }
+}
+
+impl fmt::Display for Gf256 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02x}", self.value)
+    }
+}
+
+fn same_field(left: &Gf256, right: &Gf256) {
+    if left.polynomial != right.polynomial {
+        panic!("Elements of different fields");
+    }
+}
+
+/* ----------- OPERATORS ------------ */
+// adding polynomials over GF(2) xors their coefficients
+impl Add for Gf256 {
+    type Output = Gf256;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn add(self, other: Gf256) -> Gf256 {
+        same_field(&self, &other);
+        Gf256 {
+            value: self.value ^ other.value,
+            ..self
+        }
+    }
+}
+
+impl AddAssign for Gf256 {
+    fn add_assign(&mut self, other: Gf256) {
+        *self = *self + other;
+    }
+}
+
+// in characteristic 2 subtracting is adding
+impl Sub for Gf256 {
+    type Output = Gf256;
+
+    #[allow(clippy::suspicious_arithmetic_impl)]
+    fn sub(self, other: Gf256) -> Gf256 {
+        self + other
+    }
+}
+
+impl Mul for Gf256 {
+    type Output = Gf256;
+
+    fn mul(self, other: Gf256) -> Gf256 {
+        same_field(&self, &other);
+        // see multiply, the time depends on the operands
+        Gf256 {
+            value: multiply(self.value, other.value, self.polynomial),
+            ..self
+        }
+    }
+}
+
+impl MulAssign for Gf256 {
+    fn mul_assign(&mut self, other: Gf256) {
+        *self = *self * other;
+    }
+}
+
+impl Div for Gf256 {
+    type Output = Gf256;
+
+    fn div(self, other: Gf256) -> Gf256 {
+        if other.value == 0 {
+            panic!("Division by zero");
+        }
+        self * other.inverse()
+    }
+}
+
+/* ----------- CONST ARITHMETIC ------------ */
+// Mul and inverse without the operator traits, so tables like the S-box can
+// be worked out at compile time
+pub const fn multiply(mut a: u8, mut b: u8, polynomial: u16) -> u8 {
+    let reduction = (polynomial & 0xff) as u8;
+    let mut result: u8 = 0;
+
+    // stops once the bits of b run out, so small constants are quick. Like the
+    // branches below this makes the time depend on the operands, which is the
+    // kind of leak attacks::timing looks for
+    while b != 0 {
+        if b & 1 == 1 {
+            result ^= a;
+        }
+
+        // reduce if multiplying by x carries past x^7
+        let carry = a & 0x80;
+        a <<= 1;
+        if carry != 0 {
+            a ^= reduction;
+        }
+        b >>= 1;
+    }
+
+    result
+}
+
+// a^254 by square and multiply, 0 maps to 0
+pub const fn invert(a: u8, polynomial: u16) -> u8 {
+    let (mut result, mut base, mut exponent) = (1, a, 254);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = multiply(result, base, polynomial);
+        }
+        base = multiply(base, base, polynomial);
+        exponent >>= 1;
+    }
+    result
+}
+
+
+/* ----------- LOG TABLES ------------ */
+// powers of a generator run through every nonzero element, so multiplying
+// comes down to adding logarithms modulo 255
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogTables {
+    pub polynomial: u16,
+    pub generator: u8,
+    // exp[i] = generator^i
+    pub exp: [u8; 255],
+    // log[exp[i]] = i, log[0] is unused
+    pub log: [u8; 256],
+}
+
+impl LogTables {
+    // built with the smallest generator, 0x03 for the AES polynomial
+    pub fn new(polynomial: u16) -> LogTables {
+        let one = Gf256::with_polynomial(1, polynomial);
+        let generator = (2..=255u8)
+            .find(|&candidate| order(Gf256 { value: candidate, ..one }) == 255)
+            .unwrap();
+
+        let mut exp = [0; 255];
+        let mut log = [0; 256];
+        let mut power = one;
+        for (i, entry) in exp.iter_mut().enumerate() {
+            *entry = power.value;
+            log[power.value as usize] = i as u8;
+            power *= Gf256 { value: generator, ..one };
+        }
+
+        LogTables {
+            polynomial,
+            generator,
+            exp,
+            log,
+        }
+    }
+
+    pub fn multiply(&self, a: u8, b: u8) -> u8 {
+        if a == 0 || b == 0 {
+            return 0;
+        }
+        let sum = self.log[a as usize] as usize + self.log[b as usize] as usize;
+        self.exp[sum % 255]
+    }
+
+    pub fn inverse(&self, a: u8) -> u8 {
+        if a == 0 {
+            return 0;
+        }
+        self.exp[(255 - self.log[a as usize] as usize) % 255]
+    }
+}
+
+fn order(element: Gf256) -> usize {
+    let mut power = element;
+    let mut order = 1;
+    while power.value != 1 {
+        power *= element;
+        order += 1;
+    }
+    order
+}
+
+/* ----------- POLYNOMIALS ------------ */
+// degree 8 and no factor of degree 4 or less
+pub fn is_irreducible(polynomial: u16) -> bool {
+    if polynomial >> 8 != 1 {
+        return false;
+    }
+    (2..32u16).all(|divisor| remainder(polynomial, divisor) != 0)
+}
+
+// polynomial division over GF(2)
+fn remainder(mut dividend: u16, divisor: u16) -> u16 {
+    let degree = 15 - divisor.leading_zeros();
+    while dividend != 0 && 15 - dividend.leading_zeros() >= degree {
+        dividend ^= divisor << (15 - dividend.leading_zeros() - degree);
+    }
+    dividend
+}
+
+pub fn polynomial_string(polynomial: u16) -> String {
+    let terms: Vec<String> = (0..16)
+        .rev()
+        .filter(|bit| polynomial >> bit & 1 == 1)
+        .map(|bit| match bit {
+            0 => String::from("1"),
+            1 => String::from("x"),
+            _ => format!("x^{}", bit),
+        })
+        .collect();
+
+    if terms.is_empty() {
+        return String::from("0");
+    }
+    terms.join(" + ")
+}
+
+
+/* ----------- MATRICES ------------ */
+// matrices are rows of bytes over the field given by polynomial
+pub fn matrix_rank(rows: &[Vec<u8>], polynomial: u16) -> usize {
+    let columns = rows.first().map_or(0, |row| row.len());
+    eliminate(&mut to_field(rows, polynomial), columns)
+}
+
+// None if the square matrix is singular
+pub fn invert_matrix(rows: &[Vec<u8>], polynomial: u16) -> Option<Vec<Vec<u8>>> {
+    let size = rows.len();
+    // [M | I] reduces to [I | M^-1]
+    let augmented: Vec<Vec<u8>> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut row = row.clone();
+            row.extend((0..size).map(|j| (i == j) as u8));
+            row
+        })
+        .collect();
+
+    let mut reduced = to_field(&augmented, polynomial);
+    if eliminate(&mut reduced, size) < size {
+        return None;
+    }
+    Some(reduced.iter().map(|row| row[size..].iter().map(|entry| entry.value()).collect()).collect())
+}
+
+// the polynomial is checked once, every entry copies it from the zero element
+fn to_field(rows: &[Vec<u8>], polynomial: u16) -> Vec<Vec<Gf256>> {
+    let zero = Gf256::with_polynomial(0, polynomial);
+    rows.iter()
+        .map(|row| row.iter().map(|&value| Gf256 { value, ..zero }).collect())
+        .collect()
+}
+
+// Gauss-Jordan elimination over the first columns, returns the rank
+fn eliminate(rows: &mut [Vec<Gf256>], columns: usize) -> usize {
+    let mut rank = 0;
+    for column in 0..columns {
+        let Some(pivot) = (rank..rows.len()).find(|&row| rows[row][column].value() != 0) else {
+            continue;
+        };
+        rows.swap(rank, pivot);
+        let scale = rows[rank][column].inverse();
+        for entry in rows[rank].iter_mut() {
+            *entry *= scale;
+        }
+
+        let pivot_row = rows[rank].clone();
+        for (index, row) in rows.iter_mut().enumerate() {
+            let factor = row[column];
+            if index != rank && factor.value() != 0 {
+                for (entry, &pivot_entry) in row.iter_mut().zip(&pivot_row) {
+                    *entry += factor * pivot_entry;
+                }
+            }
+        }
+        rank += 1;
+    }
+    rank
+}
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fips_197_examples() {
+        // section 4.1 and 4.2
+        assert_eq!((Gf256::new(0x57) + Gf256::new(0x83)).value(), 0xd4);
+        assert_eq!((Gf256::new(0x57) * Gf256::new(0x83)).value(), 0xc1);
+        assert_eq!((Gf256::new(0x57) * Gf256::new(0x13)).value(), 0xfe);
+        assert_eq!(Gf256::new(0x57).as_polynomial(), "x^6 + x^4 + x^2 + x + 1");
+        assert_eq!(polynomial_string(AES_POLYNOMIAL), "x^8 + x^4 + x^3 + x + 1");
+    }
+
+    #[test]
+    fn inverse_and_division() {
+        for value in 1..=255u8 {
+            let a = Gf256::new(value);
+            assert_eq!((a * a.inverse()).value(), 1);
+            assert_eq!(Gf256::new(0x42) / a * a, Gf256::new(0x42));
+        }
+        assert_eq!(Gf256::new(0).inverse().value(), 0);
+        assert_eq!(Gf256::new(0x53).inverse().value(), 0xca);
+        assert_eq!(Gf256::new(3).pow(255).value(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Division by zero")]
+    fn divide_by_zero() {
+        let _ = Gf256::new(1) / Gf256::new(0);
+    }
+
+    #[test]
+    fn log_tables_agree() {
+        let tables = LogTables::new(AES_POLYNOMIAL);
+        assert_eq!(tables.generator, 0x03);
+        assert_eq!(tables.exp[1], 0x03);
+        assert_eq!(tables.log[0x03], 1);
+
+        for a in 0..=255u8 {
+            assert_eq!(tables.inverse(a), Gf256::new(a).inverse().value());
+            for b in [0x00, 0x01, 0x02, 0x03, 0x09, 0x0b, 0x0d, 0x0e, 0x8f] {
+                assert_eq!(tables.multiply(a, b), (Gf256::new(a) * Gf256::new(b)).value());
+            }
+        }
+    }
+
+    #[test]
+    fn other_polynomials() {
+        // x^8 + x^4 + x^3 + x^2 + 1, where x itself generates the field
+        let tables = LogTables::new(0x11d);
+        assert_eq!(tables.generator, 0x02);
+        let a = Gf256::with_polynomial(0x80, 0x11d);
+        assert_eq!((a * Gf256::with_polynomial(2, 0x11d)).value(), 0x1d);
+        assert_eq!((a * a.inverse()).value(), 1);
+
+        assert_eq!((1..=0x1ffu16).filter(|&p| is_irreducible(p)).count(), 30);
+        assert!(!is_irreducible(0x101));
+        assert!(!is_irreducible(0x1b));
+    }
+
+    #[test]
+    fn matrices() {
+        let mix = vec![vec![2, 3, 1, 1], vec![1, 2, 3, 1], vec![1, 1, 2, 3], vec![3, 1, 1, 2]];
+        let inverse = vec![vec![14, 11, 13, 9], vec![9, 14, 11, 13], vec![13, 9, 14, 11], vec![11, 13, 9, 14]];
+        assert_eq!(invert_matrix(&mix, AES_POLYNOMIAL), Some(inverse));
+        assert_eq!(matrix_rank(&mix, AES_POLYNOMIAL), 4);
+
+        // the first two rows add up to the third
+        let singular = vec![vec![1, 2, 3], vec![4, 5, 6], vec![5, 7, 5]];
+        assert_eq!(invert_matrix(&singular, AES_POLYNOMIAL), None);
+        assert_eq!(matrix_rank(&singular, AES_POLYNOMIAL), 2);
+        assert_eq!(matrix_rank(&[vec![0, 0], vec![0, 7]], AES_POLYNOMIAL), 1);
+    }
+
+    #[test]
+    fn matrices_over_other_fields() {
+        // inverting x^7 needs the reduction, so the fields disagree
+        let matrix = vec![vec![0x80, 0x01], vec![0x01, 0x01]];
+        let inverse = invert_matrix(&matrix, 0x11d).unwrap();
+        assert_ne!(Some(inverse.clone()), invert_matrix(&matrix, AES_POLYNOMIAL));
+
+        // M * M^-1 is the identity when multiplied out in the same field
+        let field = |value| Gf256::with_polynomial(value, 0x11d);
+        for (i, row) in matrix.iter().enumerate() {
+            for j in 0..2 {
+                let column = inverse.iter().map(|inverse_row| inverse_row[j]);
+                let sum = row.iter().zip(column).fold(field(0), |sum, (&left, right)| sum + field(left) * field(right));
+                assert_eq!(sum.value(), (i == j) as u8);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Polynomial is not irreducible")]
+    fn matrices_need_a_field() {
+        matrix_rank(&[vec![1]], 0x101);
+    }
+
+    #[test]
+    #[should_panic(expected = "Elements of different fields")]
+    fn mixing_fields() {
+        let _ = Gf256::new(1) * Gf256::with_polynomial(1, 0x11d);
+    }
+}
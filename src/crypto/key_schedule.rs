@@ -0,0 +1,348 @@
+use super::aes;
+
+// How each word of the AES key schedule was derived, with the intermediate
+// values in the same columns as the FIPS-197 appendix A tables. Steps that
+// don't apply to a word are None.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyExpansionStep {
+    pub i: usize,
+    // w[i-1], None for the words copied straight from the key
+    pub temp: Option<Vec<u8>>,
+    pub after_rot_word: Option<Vec<u8>>,
+    // also set for the extra SubWord of 256 bit keys when i mod Nk = 4
+    pub after_sub_word: Option<Vec<u8>>,
+    pub r_con: Option<Vec<u8>>,
+    pub after_xor_with_r_con: Option<Vec<u8>>,
+    pub w_i_minus_nk: Option<Vec<u8>>,
+    pub word: Vec<u8>,
+}
+
+// key_expansion with every intermediate value kept
+pub fn key_expansion_trace(key: Vec<u8>) -> Vec<KeyExpansionStep> {
+    if ![16, 24, 32].contains(&key.len()) {
+        panic!("Invalid key length");
+    }
+
+    let n_k = key.len() / 4;
+    let num_rounds = aes::num_rounds(n_k) as usize;
+
+    let mut words: Vec<Vec<u8>> = key.chunks(4).map(|word| word.to_vec()).collect();
+    let mut steps: Vec<KeyExpansionStep> = words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| KeyExpansionStep {
+            i,
+            temp: None,
+            after_rot_word: None,
+            after_sub_word: None,
+            r_con: None,
+            after_xor_with_r_con: None,
+            w_i_minus_nk: None,
+            word: word.clone(),
+        })
+        .collect();
+
+    for i in n_k..4 * (num_rounds + 1) {
+        let temp = words[i - 1].clone();
+        let mut step = KeyExpansionStep {
+            i,
+            temp: Some(temp.clone()),
+            after_rot_word: None,
+            after_sub_word: None,
+            r_con: None,
+            after_xor_with_r_con: None,
+            w_i_minus_nk: Some(words[i - n_k].clone()),
+            word: Vec::new(),
+        };
+
+        let mut value = temp;
+        if i.is_multiple_of(n_k) {
+            let rotated = aes::rot_word(&value);
+            let substituted = aes::sub_word(&rotated);
+            let r_con = aes::r_con(i / n_k - 1);
+            value = aes::gf_add_word(substituted.clone(), r_con.clone());
+
+            step.after_rot_word = Some(rotated);
+            step.after_sub_word = Some(substituted);
+            step.r_con = Some(r_con);
+            step.after_xor_with_r_con = Some(value.clone());
+        } else if n_k > 6 && i % n_k == 4 {
+            value = aes::sub_word(&value);
+            step.after_sub_word = Some(value.clone());
+        }
+
+        step.word = aes::gf_add_word(words[i - n_k].clone(), value);
+        words.push(step.word.clone());
+        steps.push(step);
+    }
+
+    steps
+}
+
+// Runs the schedule backwards from round keys to the cipher key. round_keys is
+// one or more consecutive round keys starting at round. A round key is 4 words,
+// all of an AES-128 key but short of the 6 or 8 words in AES-192 and AES-256
+// keys, so those need the next round key as well.
+pub fn invert_key_schedule(round_keys: &[u8], round: usize, key_bits: u16) -> Vec<u8> {
+    if ![128, 192, 256].contains(&key_bits) {
+        panic!("Invalid key size");
+    }
+    if round_keys.is_empty() || !round_keys.len().is_multiple_of(16) {
+        panic!("Invalid round key length");
+    }
+
+    let n_k = key_bits as usize / 32;
+    let words: Vec<Vec<u8>> = round_keys.chunks(4).map(|word| word.to_vec()).collect();
+    if words.len() < n_k {
+        panic!("Not enough round keys for this key size");
+    }
+
+    let total_words = 4 * (aes::num_rounds(n_k) as usize + 1);
+    if round * 4 + words.len() > total_words {
+        panic!("Invalid round");
+    }
+
+    aes::invert_key_expansion(&words[..n_k], round * 4)
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // rows from i = Nk on, columns as in the standard with - for the empty cells
+    const APPENDIX_A1: &str = "\
+        04 09cf4f3c cf4f3c09 8a84eb01 01000000 8b84eb01 2b7e1516 a0fafe17\n\
+        05 a0fafe17 - - - - 28aed2a6 88542cb1\n\
+        06 88542cb1 - - - - abf71588 23a33939\n\
+        07 23a33939 - - - - 09cf4f3c 2a6c7605\n\
+        08 2a6c7605 6c76052a 50386be5 02000000 52386be5 a0fafe17 f2c295f2\n\
+        09 f2c295f2 - - - - 88542cb1 7a96b943\n\
+        10 7a96b943 - - - - 23a33939 5935807a\n\
+        11 5935807a - - - - 2a6c7605 7359f67f\n\
+        12 7359f67f 59f67f73 cb42d28f 04000000 cf42d28f f2c295f2 3d80477d\n\
+        13 3d80477d - - - - 7a96b943 4716fe3e\n\
+        14 4716fe3e - - - - 5935807a 1e237e44\n\
+        15 1e237e44 - - - - 7359f67f 6d7a883b\n\
+        16 6d7a883b 7a883b6d dac4e23c 08000000 d2c4e23c 3d80477d ef44a541\n\
+        17 ef44a541 - - - - 4716fe3e a8525b7f\n\
+        18 a8525b7f - - - - 1e237e44 b671253b\n\
+        19 b671253b - - - - 6d7a883b db0bad00\n\
+        20 db0bad00 0bad00db 2b9563b9 10000000 3b9563b9 ef44a541 d4d1c6f8\n\
+        21 d4d1c6f8 - - - - a8525b7f 7c839d87\n\
+        22 7c839d87 - - - - b671253b caf2b8bc\n\
+        23 caf2b8bc - - - - db0bad00 11f915bc\n\
+        24 11f915bc f915bc11 99596582 20000000 b9596582 d4d1c6f8 6d88a37a\n\
+        25 6d88a37a - - - - 7c839d87 110b3efd\n\
+        26 110b3efd - - - - caf2b8bc dbf98641\n\
+        27 dbf98641 - - - - 11f915bc ca0093fd\n\
+        28 ca0093fd 0093fdca 63dc5474 40000000 23dc5474 6d88a37a 4e54f70e\n\
+        29 4e54f70e - - - - 110b3efd 5f5fc9f3\n\
+        30 5f5fc9f3 - - - - dbf98641 84a64fb2\n\
+        31 84a64fb2 - - - - ca0093fd 4ea6dc4f\n\
+        32 4ea6dc4f a6dc4f4e 2486842f 80000000 a486842f 4e54f70e ead27321\n\
+        33 ead27321 - - - - 5f5fc9f3 b58dbad2\n\
+        34 b58dbad2 - - - - 84a64fb2 312bf560\n\
+        35 312bf560 - - - - 4ea6dc4f 7f8d292f\n\
+        36 7f8d292f 8d292f7f 5da515d2 1b000000 46a515d2 ead27321 ac7766f3\n\
+        37 ac7766f3 - - - - b58dbad2 19fadc21\n\
+        38 19fadc21 - - - - 312bf560 28d12941\n\
+        39 28d12941 - - - - 7f8d292f 575c006e\n\
+        40 575c006e 5c006e57 4a639f5b 36000000 7c639f5b ac7766f3 d014f9a8\n\
+        41 d014f9a8 - - - - 19fadc21 c9ee2589\n\
+        42 c9ee2589 - - - - 28d12941 e13f0cc8\n\
+        43 e13f0cc8 - - - - 575c006e b6630ca6";
+
+    const APPENDIX_A2: &str = "\
+        06 522c6b7b 2c6b7b52 717f2100 01000000 707f2100 8e73b0f7 fe0c91f7\n\
+        07 fe0c91f7 - - - - da0e6452 2402f5a5\n\
+        08 2402f5a5 - - - - c810f32b ec12068e\n\
+        09 ec12068e - - - - 809079e5 6c827f6b\n\
+        10 6c827f6b - - - - 62f8ead2 0e7a95b9\n\
+        11 0e7a95b9 - - - - 522c6b7b 5c56fec2\n\
+        12 5c56fec2 56fec25c b1bb254a 02000000 b3bb254a fe0c91f7 4db7b4bd\n\
+        13 4db7b4bd - - - - 2402f5a5 69b54118\n\
+        14 69b54118 - - - - ec12068e 85a74796\n\
+        15 85a74796 - - - - 6c827f6b e92538fd\n\
+        16 e92538fd - - - - 0e7a95b9 e75fad44\n\
+        17 e75fad44 - - - - 5c56fec2 bb095386\n\
+        18 bb095386 095386bb 01ed44ea 04000000 05ed44ea 4db7b4bd 485af057\n\
+        19 485af057 - - - - 69b54118 21efb14f\n\
+        20 21efb14f - - - - 85a74796 a448f6d9\n\
+        21 a448f6d9 - - - - e92538fd 4d6dce24\n\
+        22 4d6dce24 - - - - e75fad44 aa326360\n\
+        23 aa326360 - - - - bb095386 113b30e6\n\
+        24 113b30e6 3b30e611 e2048e82 08000000 ea048e82 485af057 a25e7ed5\n\
+        25 a25e7ed5 - - - - 21efb14f 83b1cf9a\n\
+        26 83b1cf9a - - - - a448f6d9 27f93943\n\
+        27 27f93943 - - - - 4d6dce24 6a94f767\n\
+        28 6a94f767 - - - - aa326360 c0a69407\n\
+        29 c0a69407 - - - - 113b30e6 d19da4e1\n\
+        30 d19da4e1 9da4e1d1 5e49f83e 10000000 4e49f83e a25e7ed5 ec1786eb\n\
+        31 ec1786eb - - - - 83b1cf9a 6fa64971\n\
+        32 6fa64971 - - - - 27f93943 485f7032\n\
+        33 485f7032 - - - - 6a94f767 22cb8755\n\
+        34 22cb8755 - - - - c0a69407 e26d1352\n\
+        35 e26d1352 - - - - d19da4e1 33f0b7b3\n\
+        36 33f0b7b3 f0b7b333 8ca96dc3 20000000 aca96dc3 ec1786eb 40beeb28\n\
+        37 40beeb28 - - - - 6fa64971 2f18a259\n\
+        38 2f18a259 - - - - 485f7032 6747d26b\n\
+        39 6747d26b - - - - 22cb8755 458c553e\n\
+        40 458c553e - - - - e26d1352 a7e1466c\n\
+        41 a7e1466c - - - - 33f0b7b3 9411f1df\n\
+        42 9411f1df 11f1df94 82a19e22 40000000 c2a19e22 40beeb28 821f750a\n\
+        43 821f750a - - - - 2f18a259 ad07d753\n\
+        44 ad07d753 - - - - 6747d26b ca400538\n\
+        45 ca400538 - - - - 458c553e 8fcc5006\n\
+        46 8fcc5006 - - - - a7e1466c 282d166a\n\
+        47 282d166a - - - - 9411f1df bc3ce7b5\n\
+        48 bc3ce7b5 3ce7b5bc eb94d565 80000000 6b94d565 821f750a e98ba06f\n\
+        49 e98ba06f - - - - ad07d753 448c773c\n\
+        50 448c773c - - - - ca400538 8ecc7204\n\
+        51 8ecc7204 - - - - 8fcc5006 01002202";
+
+    const APPENDIX_A3: &str = "\
+        08 0914dff4 14dff409 fa9ebf01 01000000 fb9ebf01 603deb10 9ba35411\n\
+        09 9ba35411 - - - - 15ca71be 8e6925af\n\
+        10 8e6925af - - - - 2b73aef0 a51a8b5f\n\
+        11 a51a8b5f - - - - 857d7781 2067fcde\n\
+        12 2067fcde - b785b01d - - 1f352c07 a8b09c1a\n\
+        13 a8b09c1a - - - - 3b6108d7 93d194cd\n\
+        14 93d194cd - - - - 2d9810a3 be49846e\n\
+        15 be49846e - - - - 0914dff4 b75d5b9a\n\
+        16 b75d5b9a 5d5b9ab7 4c39b8a9 02000000 4e39b8a9 9ba35411 d59aecb8\n\
+        17 d59aecb8 - - - - 8e6925af 5bf3c917\n\
+        18 5bf3c917 - - - - a51a8b5f fee94248\n\
+        19 fee94248 - - - - 2067fcde de8ebe96\n\
+        20 de8ebe96 - 1d19ae90 - - a8b09c1a b5a9328a\n\
+        21 b5a9328a - - - - 93d194cd 2678a647\n\
+        22 2678a647 - - - - be49846e 98312229\n\
+        23 98312229 - - - - b75d5b9a 2f6c79b3\n\
+        24 2f6c79b3 6c79b32f 50b66d15 04000000 54b66d15 d59aecb8 812c81ad\n\
+        25 812c81ad - - - - 5bf3c917 dadf48ba\n\
+        26 dadf48ba - - - - fee94248 24360af2\n\
+        27 24360af2 - - - - de8ebe96 fab8b464\n\
+        28 fab8b464 - 2d6c8d43 - - b5a9328a 98c5bfc9\n\
+        29 98c5bfc9 - - - - 2678a647 bebd198e\n\
+        30 bebd198e - - - - 98312229 268c3ba7\n\
+        31 268c3ba7 - - - - 2f6c79b3 09e04214\n\
+        32 09e04214 e0421409 e12cfa01 08000000 e92cfa01 812c81ad 68007bac\n\
+        33 68007bac - - - - dadf48ba b2df3316\n\
+        34 b2df3316 - - - - 24360af2 96e939e4\n\
+        35 96e939e4 - - - - fab8b464 6c518d80\n\
+        36 6c518d80 - 50d15dcd - - 98c5bfc9 c814e204\n\
+        37 c814e204 - - - - bebd198e 76a9fb8a\n\
+        38 76a9fb8a - - - - 268c3ba7 5025c02d\n\
+        39 5025c02d - - - - 09e04214 59c58239\n\
+        40 59c58239 c5823959 a61312cb 10000000 b61312cb 68007bac de136967\n\
+        41 de136967 - - - - b2df3316 6ccc5a71\n\
+        42 6ccc5a71 - - - - 96e939e4 fa256395\n\
+        43 fa256395 - - - - 6c518d80 9674ee15\n\
+        44 9674ee15 - 90922859 - - c814e204 5886ca5d\n\
+        45 5886ca5d - - - - 76a9fb8a 2e2f31d7\n\
+        46 2e2f31d7 - - - - 5025c02d 7e0af1fa\n\
+        47 7e0af1fa - - - - 59c58239 27cf73c3\n\
+        48 27cf73c3 cf73c327 8a8f2ecc 20000000 aa8f2ecc de136967 749c47ab\n\
+        49 749c47ab - - - - 6ccc5a71 18501dda\n\
+        50 18501dda - - - - fa256395 e2757e4f\n\
+        51 e2757e4f - - - - 9674ee15 7401905a\n\
+        52 7401905a - 927c60be - - 5886ca5d cafaaae3\n\
+        53 cafaaae3 - - - - 2e2f31d7 e4d59b34\n\
+        54 e4d59b34 - - - - 7e0af1fa 9adf6ace\n\
+        55 9adf6ace - - - - 27cf73c3 bd10190d\n\
+        56 bd10190d 10190dbd cad4d77a 40000000 8ad4d77a 749c47ab fe4890d1\n\
+        57 fe4890d1 - - - - 18501dda e6188d0b\n\
+        58 e6188d0b - - - - e2757e4f 046df344\n\
+        59 046df344 - - - - 7401905a 706c631e";
+
+    fn check_table(key: &str, table: &str) {
+        let key = hex::decode(key).unwrap();
+        let n_k = key.len() / 4;
+        let trace = key_expansion_trace(key.clone());
+
+        let cell = |value: &Option<Vec<u8>>| value.as_ref().map_or("-".to_string(), hex::encode);
+        let rows: Vec<String> = trace[n_k..]
+            .iter()
+            .map(|step| {
+                format!(
+                    "{:02} {} {} {} {} {} {} {}",
+                    step.i,
+                    cell(&step.temp),
+                    cell(&step.after_rot_word),
+                    cell(&step.after_sub_word),
+                    cell(&step.r_con),
+                    cell(&step.after_xor_with_r_con),
+                    cell(&step.w_i_minus_nk),
+                    hex::encode(&step.word)
+                )
+            })
+            .collect();
+        assert_eq!(rows, table.lines().collect::<Vec<_>>());
+
+        // the words copied from the key, and the same words key_expansion gives
+        assert!(trace[..n_k].iter().all(|step| step.temp.is_none()));
+        let words: Vec<Vec<u8>> = trace.into_iter().map(|step| step.word).collect();
+        assert_eq!(words, aes::key_expansion(key));
+    }
+
+    #[test]
+    fn inverts_from_every_round() {
+        for key in [
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+        ] {
+            let key = hex::decode(key).unwrap();
+            let key_bits = key.len() as u16 * 8;
+            let schedule = aes::key_expansion(key.clone()).concat();
+            let num_rounds = aes::num_rounds(key.len() / 4) as usize;
+
+            // one round key for AES-128, two for the longer keys
+            let round_keys = if key_bits == 128 { 1 } else { 2 };
+            for round in 0..=num_rounds + 1 - round_keys {
+                let round_key = &schedule[round * 16..(round + round_keys) * 16];
+                assert_eq!(invert_key_schedule(round_key, round, key_bits), key, "round {}", round);
+            }
+        }
+    }
+
+    #[test]
+    fn inverts_random_keys() {
+        let key = crate::key_gen(128);
+        let schedule = aes::key_expansion(key.clone()).concat();
+        assert_eq!(invert_key_schedule(&schedule[160..], 10, 128), key);
+
+        let key = crate::key_gen(256);
+        let schedule = aes::key_expansion(key.clone()).concat();
+        assert_eq!(invert_key_schedule(&schedule[13 * 16..], 13, 256), key);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough round keys for this key size")]
+    fn one_round_key_is_not_enough_for_256() {
+        invert_key_schedule(&[0; 16], 5, 256);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid round")]
+    fn rejects_rounds_past_the_end() {
+        invert_key_schedule(&[0; 16], 11, 128);
+    }
+
+    #[test]
+    fn appendix_a1_128() {
+        check_table("2b7e151628aed2a6abf7158809cf4f3c", APPENDIX_A1);
+    }
+
+    #[test]
+    fn appendix_a2_192() {
+        check_table("8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b", APPENDIX_A2);
+    }
+
+    #[test]
+    fn appendix_a3_256() {
+        check_table("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4", APPENDIX_A3);
+    }
+}
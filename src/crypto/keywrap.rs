@@ -0,0 +1,300 @@
+use super::block::{Aes, BlockCipher};
+use super::Error;
+
+// RFC 3394 default initial value
+const DEFAULT_IV: [u8; 8] = [0xa6; 8];
+// RFC 5649 alternative initial value prefix, followed by the 32-bit message length
+const AIV_PREFIX: [u8; 4] = [0xa6, 0x59, 0x59, 0xa6];
+
+/* ----------- RFC 3394 ------------ */
+pub fn aes_key_wrap(key_data: Vec<u8>, kek: Vec<u8>) -> Vec<u8> {
+    if key_data.len() < 16 || !key_data.len().is_multiple_of(8) {
+        panic!("Invalid key data length");
+    }
+
+    let cipher = Aes::new(kek);
+
+    wrap(DEFAULT_IV, &key_data, &cipher)
+}
+
+pub fn aes_key_unwrap(wrapped: Vec<u8>, kek: Vec<u8>) -> Result<Vec<u8>, Error> {
+    if wrapped.len() < 24 || !wrapped.len().is_multiple_of(8) {
+        return Err(Error::InvalidLength);
+    }
+
+    let cipher = Aes::new(kek);
+
+    let (iv, key_data) = unwrap(&wrapped, &cipher);
+    if iv != DEFAULT_IV {
+        return Err(Error::IntegrityCheckFailed);
+    }
+
+    Ok(key_data)
+}
+
+/* ----------- RFC 5649 ------------ */
+pub fn aes_key_wrap_pad(key_data: Vec<u8>, kek: Vec<u8>) -> Vec<u8> {
+    if key_data.is_empty() || key_data.len() > u32::MAX as usize {
+        panic!("Invalid key data length");
+    }
+
+    let cipher = Aes::new(kek);
+
+    let mut iv = [0; 8];
+    iv[..4].copy_from_slice(&AIV_PREFIX);
+    iv[4..].copy_from_slice(&(key_data.len() as u32).to_be_bytes());
+
+    // pad with zeros up to the next multiple of 8
+    let mut padded = key_data;
+    padded.resize(padded.len().div_ceil(8) * 8, 0);
+
+    // a single semiblock is encrypted directly as one block
+    if padded.len() == 8 {
+        let mut block = iv.to_vec();
+        block.extend_from_slice(&padded);
+        return cipher.encrypt_block(block);
+    }
+
+    wrap(iv, &padded, &cipher)
+}
+
+pub fn aes_key_unwrap_pad(wrapped: Vec<u8>, kek: Vec<u8>) -> Result<Vec<u8>, Error> {
+    if wrapped.len() < 16 || !wrapped.len().is_multiple_of(8) {
+        return Err(Error::InvalidLength);
+    }
+
+    let cipher = Aes::new(kek);
+
+    let (iv, mut padded) = if wrapped.len() == 16 {
+        let mut block = cipher.decrypt_block(wrapped);
+        let key_data = block.split_off(8);
+        let mut iv = [0; 8];
+        iv.copy_from_slice(&block);
+        (iv, key_data)
+    } else {
+        unwrap(&wrapped, &cipher)
+    };
+
+    if iv[..4] != AIV_PREFIX {
+        return Err(Error::IntegrityCheckFailed);
+    }
+
+    // the message length must fall inside the last semiblock
+    let length = u32::from_be_bytes([iv[4], iv[5], iv[6], iv[7]]) as usize;
+    if length > padded.len() || length + 8 <= padded.len() {
+        return Err(Error::IntegrityCheckFailed);
+    }
+
+    // and everything after it must be zero padding
+    if padded[length..].iter().any(|&byte| byte != 0) {
+        return Err(Error::IntegrityCheckFailed);
+    }
+
+    padded.truncate(length);
+    Ok(padded)
+}
+
+/* ----------- WRAPPING FUNCTIONS ------------ */
+// W from RFC 3394 section 2.2.1
+fn wrap(iv: [u8; 8], key_data: &[u8], cipher: &Aes) -> Vec<u8> {
+    let n = key_data.len() / 8;
+    let mut a = iv;
+    let mut r: Vec<u8> = key_data.to_vec();
+
+    for j in 0..6 {
+        for i in 0..n {
+            let mut block = a.to_vec();
+            block.extend_from_slice(&r[i * 8..(i + 1) * 8]);
+
+            let b = cipher.encrypt_block(block);
+
+            let t = ((n * j) + i + 1) as u64;
+            a.copy_from_slice(&b[..8]);
+            for (byte, t_byte) in a.iter_mut().zip(t.to_be_bytes()) {
+                *byte ^= t_byte;
+            }
+            r[i * 8..(i + 1) * 8].copy_from_slice(&b[8..]);
+        }
+    }
+
+    let mut wrapped = a.to_vec();
+    wrapped.append(&mut r);
+    wrapped
+}
+
+// W^-1 from RFC 3394 section 2.2.2, returning the recovered initial value
+fn unwrap(wrapped: &[u8], cipher: &Aes) -> ([u8; 8], Vec<u8>) {
+    let n = wrapped.len() / 8 - 1;
+    let mut a = [0; 8];
+    a.copy_from_slice(&wrapped[..8]);
+    let mut r: Vec<u8> = wrapped[8..].to_vec();
+
+    for j in (0..6).rev() {
+        for i in (0..n).rev() {
+            let t = ((n * j) + i + 1) as u64;
+            for (byte, t_byte) in a.iter_mut().zip(t.to_be_bytes()) {
+                *byte ^= t_byte;
+            }
+
+            let mut block = a.to_vec();
+            block.extend_from_slice(&r[i * 8..(i + 1) * 8]);
+
+            let b = cipher.decrypt_block(block);
+
+            a.copy_from_slice(&b[..8]);
+            r[i * 8..(i + 1) * 8].copy_from_slice(&b[8..]);
+        }
+    }
+
+    (a, r)
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kek(length: u8) -> Vec<u8> {
+        (0..length).collect()
+    }
+
+    #[test]
+    fn wrap_128_data_128_kek() {
+        let key_data = hex::decode("00112233445566778899AABBCCDDEEFF").unwrap();
+        let expected = hex::decode("1FA68B0A8112B447AEF34BD8FB5A7B829D3E862371D2CFE5").unwrap();
+
+        let wrapped = aes_key_wrap(key_data.clone(), kek(16));
+        assert_eq!(wrapped, expected);
+        assert_eq!(aes_key_unwrap(wrapped, kek(16)), Ok(key_data));
+    }
+
+    #[test]
+    fn wrap_128_data_192_kek() {
+        let key_data = hex::decode("00112233445566778899AABBCCDDEEFF").unwrap();
+        let expected = hex::decode("96778B25AE6CA435F92B5B97C050AED2468AB8A17AD84E5D").unwrap();
+
+        let wrapped = aes_key_wrap(key_data.clone(), kek(24));
+        assert_eq!(wrapped, expected);
+        assert_eq!(aes_key_unwrap(wrapped, kek(24)), Ok(key_data));
+    }
+
+    #[test]
+    fn wrap_128_data_256_kek() {
+        let key_data = hex::decode("00112233445566778899AABBCCDDEEFF").unwrap();
+        let expected = hex::decode("64E8C3F9CE0F5BA263E9777905818A2A93C8191E7D6E8AE7").unwrap();
+
+        let wrapped = aes_key_wrap(key_data.clone(), kek(32));
+        assert_eq!(wrapped, expected);
+        assert_eq!(aes_key_unwrap(wrapped, kek(32)), Ok(key_data));
+    }
+
+    #[test]
+    fn wrap_192_data_192_kek() {
+        let key_data = hex::decode("00112233445566778899AABBCCDDEEFF0001020304050607").unwrap();
+        let expected = hex::decode(
+            "031D33264E15D33268F24EC260743EDCE1C6C7DDEE725A936BA814915C6762D2",
+        )
+        .unwrap();
+
+        let wrapped = aes_key_wrap(key_data.clone(), kek(24));
+        assert_eq!(wrapped, expected);
+        assert_eq!(aes_key_unwrap(wrapped, kek(24)), Ok(key_data));
+    }
+
+    #[test]
+    fn wrap_192_data_256_kek() {
+        let key_data = hex::decode("00112233445566778899AABBCCDDEEFF0001020304050607").unwrap();
+        let expected = hex::decode(
+            "A8F9BC1612C68B3FF6E6F4FBE30E71E4769C8B80A32CB8958CD5D17D6B254DA1",
+        )
+        .unwrap();
+
+        let wrapped = aes_key_wrap(key_data.clone(), kek(32));
+        assert_eq!(wrapped, expected);
+        assert_eq!(aes_key_unwrap(wrapped, kek(32)), Ok(key_data));
+    }
+
+    #[test]
+    fn wrap_256_data_256_kek() {
+        let key_data = hex::decode(
+            "00112233445566778899AABBCCDDEEFF000102030405060708090A0B0C0D0E0F",
+        )
+        .unwrap();
+        let expected = hex::decode(
+            "28C9F404C4B810F4CBCCB35CFB87F8263F5786E2D80ED326CBC7F0E71A99F43BFB988B9B7A02DD21",
+        )
+        .unwrap();
+
+        let wrapped = aes_key_wrap(key_data.clone(), kek(32));
+        assert_eq!(wrapped, expected);
+        assert_eq!(aes_key_unwrap(wrapped, kek(32)), Ok(key_data));
+    }
+
+    #[test]
+    fn unwrap_rejects_tampered() {
+        let key_data = hex::decode("00112233445566778899AABBCCDDEEFF").unwrap();
+        let mut wrapped = aes_key_wrap(key_data, kek(16));
+        wrapped[10] ^= 0x01;
+
+        assert_eq!(aes_key_unwrap(wrapped, kek(16)), Err(Error::IntegrityCheckFailed));
+    }
+
+    #[test]
+    fn unwrap_rejects_bad_length() {
+        assert_eq!(aes_key_unwrap(vec![0; 20], kek(16)), Err(Error::InvalidLength));
+        assert_eq!(aes_key_unwrap(vec![0; 16], kek(16)), Err(Error::InvalidLength));
+    }
+
+    #[test]
+    fn wrap_pad_20_octets() {
+        let kek = hex::decode("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8").unwrap();
+        let key_data = hex::decode("c37b7e6492584340bed12207808941155068f738").unwrap();
+        let expected = hex::decode(
+            "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a",
+        )
+        .unwrap();
+
+        let wrapped = aes_key_wrap_pad(key_data.clone(), kek.clone());
+        assert_eq!(wrapped, expected);
+        assert_eq!(aes_key_unwrap_pad(wrapped, kek), Ok(key_data));
+    }
+
+    #[test]
+    fn wrap_pad_7_octets() {
+        let kek = hex::decode("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8").unwrap();
+        let key_data = hex::decode("466f7250617369").unwrap();
+        let expected = hex::decode("afbeb0f07dfbf5419200f2ccb50bb24f").unwrap();
+
+        let wrapped = aes_key_wrap_pad(key_data.clone(), kek.clone());
+        assert_eq!(wrapped, expected);
+        assert_eq!(aes_key_unwrap_pad(wrapped, kek), Ok(key_data));
+    }
+
+    #[test]
+    fn unwrap_pad_rejects_tampered() {
+        let kek = hex::decode("5840df6e29b02af1ab493b705bf16ea1ae8338f4dcc176a8").unwrap();
+
+        let mut wrapped = hex::decode(
+            "138bdeaa9b8fa7fc61f97742e72248ee5ae6ae5360d1ae6a5f54f373fa543b6a",
+        )
+        .unwrap();
+        wrapped[0] ^= 0x80;
+        assert_eq!(aes_key_unwrap_pad(wrapped, kek.clone()), Err(Error::IntegrityCheckFailed));
+
+        let mut wrapped = hex::decode("afbeb0f07dfbf5419200f2ccb50bb24f").unwrap();
+        wrapped[15] ^= 0x01;
+        assert_eq!(aes_key_unwrap_pad(wrapped, kek), Err(Error::IntegrityCheckFailed));
+    }
+
+    #[test]
+    fn wrap_generated_key() {
+        let master = crate::key_gen(256);
+        let key = crate::key_gen(192);
+
+        let wrapped = aes_key_wrap(key.clone(), master.clone());
+        assert_eq!(wrapped.len(), key.len() + 8);
+        assert_eq!(aes_key_unwrap(wrapped, master), Ok(key));
+    }
+}
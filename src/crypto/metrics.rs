@@ -0,0 +1,239 @@
+use std::fmt::Write;
+
+use super::aes::Components;
+use super::gf256;
+
+// Measures of how well an S-box and a MixColumns matrix resist differential
+// and linear cryptanalysis. The AES S-box has differential uniformity 4 and
+// nonlinearity 112, the best known for an 8 bit permutation, and the AES
+// matrix is MDS with branch number 5.
+
+/* ----------- S-BOX ------------ */
+// ddt[a][b] counts the inputs x with S(x) ^ S(x ^ a) = b
+pub fn difference_distribution_table(s_box: &[u8; 256]) -> Vec<Vec<u16>> {
+    let mut ddt = vec![vec![0; 256]; 256];
+    for (a, row) in ddt.iter_mut().enumerate() {
+        for x in 0..256 {
+            row[(s_box[x] ^ s_box[x ^ a]) as usize] += 1;
+        }
+    }
+    ddt
+}
+
+// the largest count for a nonzero input difference, lower is better
+pub fn differential_uniformity(s_box: &[u8; 256]) -> u16 {
+    let ddt = difference_distribution_table(s_box);
+    ddt[1..].iter().flatten().copied().max().unwrap()
+}
+
+// lat[a][b] is how many inputs x have a.x = b.S(x), minus 128, where a.x is
+// the parity of the masked bits. Each column b is a Walsh-Hadamard transform.
+pub fn linear_approximation_table(s_box: &[u8; 256]) -> Vec<Vec<i16>> {
+    let mut lat = vec![vec![0; 256]; 256];
+    for b in 0..256 {
+        let mut walsh: Vec<i32> = s_box
+            .iter()
+            .map(|&output| if (output & b as u8).count_ones().is_multiple_of(2) { 1 } else { -1 })
+            .collect();
+
+        let mut half = 1;
+        while half < 256 {
+            for start in (0..256).step_by(half * 2) {
+                for i in start..start + half {
+                    let (left, right) = (walsh[i], walsh[i + half]);
+                    walsh[i] = left + right;
+                    walsh[i + half] = left - right;
+                }
+            }
+            half *= 2;
+        }
+
+        for (a, row) in lat.iter_mut().enumerate() {
+            row[b] = (walsh[a] / 2) as i16;
+        }
+    }
+    lat
+}
+
+// distance to the nearest affine function, higher is better
+pub fn nonlinearity(s_box: &[u8; 256]) -> u16 {
+    let lat = linear_approximation_table(s_box);
+    let bias = lat.iter().flat_map(|row| row[1..].iter()).map(|entry| entry.unsigned_abs()).max().unwrap();
+    128 - bias
+}
+
+pub fn fixed_points(s_box: &[u8; 256]) -> Vec<u8> {
+    (0..=255u8).filter(|&x| s_box[x as usize] == x).collect()
+}
+
+// inputs mapped to their complement
+pub fn opposite_fixed_points(s_box: &[u8; 256]) -> Vec<u8> {
+    (0..=255u8).filter(|&x| s_box[x as usize] == !x).collect()
+}
+
+/* ----------- MIX MATRIX ------------ */
+// the least number of active bytes going in and out, over nonzero inputs.
+// Some input supported on columns S comes out zero on rows R exactly when
+// M[R][S] has a nonzero kernel, so the search runs over submatrices.
+pub fn branch_number(matrix: &[[u8; 4]; 4]) -> usize {
+    let mut best = 8;
+    for columns in 1..16usize {
+        for rows in 0..16usize {
+            let submatrix: Vec<Vec<u8>> = (0..4)
+                .filter(|row| rows >> row & 1 == 1)
+                .map(|row| (0..4).filter(|column| columns >> column & 1 == 1).map(|column| matrix[row][column]).collect())
+                .collect();
+
+            let inputs = columns.count_ones() as usize;
+            if gf256::matrix_rank(&submatrix, gf256::AES_POLYNOMIAL) < inputs {
+                best = best.min(inputs + 4 - rows.count_ones() as usize);
+            }
+        }
+    }
+    best
+}
+
+// the same for masks going through the matrix, which use its transpose
+pub fn linear_branch_number(matrix: &[[u8; 4]; 4]) -> usize {
+    let mut transpose = [[0; 4]; 4];
+    for (row, values) in matrix.iter().enumerate() {
+        for (column, &value) in values.iter().enumerate() {
+            transpose[column][row] = value;
+        }
+    }
+    branch_number(&transpose)
+}
+
+/* ----------- REPORT ------------ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub differential_uniformity: u16,
+    pub nonlinearity: u16,
+    // largest |lat[a][b]| for a nonzero output mask
+    pub max_linear_bias: u16,
+    pub fixed_points: Vec<u8>,
+    pub opposite_fixed_points: Vec<u8>,
+    pub branch_number: usize,
+    pub linear_branch_number: usize,
+}
+
+pub fn report(components: &Components) -> Report {
+    let nonlinearity = nonlinearity(&components.s_box);
+    Report {
+        differential_uniformity: differential_uniformity(&components.s_box),
+        nonlinearity,
+        max_linear_bias: 128 - nonlinearity,
+        fixed_points: fixed_points(&components.s_box),
+        opposite_fixed_points: opposite_fixed_points(&components.s_box),
+        branch_number: branch_number(&components.mix_columns),
+        linear_branch_number: linear_branch_number(&components.mix_columns),
+    }
+}
+
+impl Report {
+    // an MDS 4x4 matrix reaches the bound of 5
+    pub fn is_mds(&self) -> bool {
+        self.branch_number == 5
+    }
+
+    pub fn render(&self) -> String {
+        let list = |points: &[u8]| match points.len() {
+            0 => String::from("none"),
+            _ => points.iter().map(|point| format!("{:02x}", point)).collect::<Vec<_>>().join(" "),
+        };
+
+        let mut output = String::new();
+        writeln!(output, "S-box").unwrap();
+        writeln!(
+            output,
+            "  differential uniformity  {}   best difference holds with probability {}/256",
+            self.differential_uniformity, self.differential_uniformity
+        )
+        .unwrap();
+        writeln!(output, "  nonlinearity             {}", self.nonlinearity).unwrap();
+        writeln!(
+            output,
+            "  max linear bias          {}   best approximation holds for {}/256 inputs",
+            self.max_linear_bias,
+            128 + self.max_linear_bias
+        )
+        .unwrap();
+        writeln!(output, "  fixed points             {}", list(&self.fixed_points)).unwrap();
+        writeln!(output, "  opposite fixed points    {}", list(&self.opposite_fixed_points)).unwrap();
+        writeln!(output, "MixColumns matrix").unwrap();
+        writeln!(
+            output,
+            "  branch number            {}{}",
+            self.branch_number,
+            if self.is_mds() { "   MDS" } else { "" }
+        )
+        .unwrap();
+        writeln!(output, "  linear branch number     {}", self.linear_branch_number).unwrap();
+        output
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aes_scores() {
+        let report = report(&Components::aes());
+
+        assert_eq!(report.differential_uniformity, 4);
+        assert_eq!(report.nonlinearity, 112);
+        assert_eq!(report.max_linear_bias, 16);
+        assert!(report.fixed_points.is_empty());
+        assert!(report.opposite_fixed_points.is_empty());
+        assert_eq!(report.branch_number, 5);
+        assert_eq!(report.linear_branch_number, 5);
+        assert!(report.is_mds());
+
+        let text = report.render();
+        assert!(text.contains("  nonlinearity             112\n"));
+        assert!(text.contains("  branch number            5   MDS\n"));
+        assert!(text.contains("  fixed points             none\n"));
+    }
+
+    #[test]
+    fn tables() {
+        let s_box = Components::aes().s_box;
+
+        let ddt = difference_distribution_table(&s_box);
+        assert_eq!(ddt[0][0], 256);
+        assert!(ddt.iter().all(|row| row.iter().sum::<u16>() == 256));
+
+        let lat = linear_approximation_table(&s_box);
+        assert_eq!(lat[0][0], 128);
+        assert!(lat[0][1..].iter().all(|&entry| entry == 0));
+        // check an entry by counting
+        let count = (0..256).filter(|&x: &usize| (x & 0x1f).count_ones() % 2 == (s_box[x] & 0xa3).count_ones() % 2).count();
+        assert_eq!(lat[0x1f][0xa3], count as i16 - 128);
+    }
+
+    #[test]
+    fn weak_components() {
+        // the identity S-box is linear and fixes everything
+        let mut identity = [0; 256];
+        for (i, entry) in identity.iter_mut().enumerate() {
+            *entry = i as u8;
+        }
+        assert_eq!(nonlinearity(&identity), 0);
+        assert_eq!(differential_uniformity(&identity), 256);
+        assert_eq!(fixed_points(&identity).len(), 256);
+
+        // one active byte goes to two active bytes
+        let matrix = [[1, 1, 0, 0], [0, 1, 1, 0], [0, 0, 1, 1], [1, 0, 0, 2]];
+        assert_eq!(branch_number(&matrix), 3);
+        let report = report(&Components::new(Components::aes().s_box, matrix));
+        assert!(!report.is_mds());
+        assert!(report.render().contains("  branch number            3\n"));
+
+        // a single zero entry already breaks MDS
+        assert_eq!(branch_number(&[[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 1, 0], [0, 0, 0, 1]]), 2);
+    }
+}
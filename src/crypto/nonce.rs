@@ -0,0 +1,97 @@
+use std::collections::{HashMap, HashSet};
+
+use super::Error;
+
+// Remembers which nonces have been used with which key, so a caller that opts
+// in gets an error instead of silently reusing a keystream.
+//
+// Keys are held in memory as given, this is meant for demos and tests.
+#[derive(Debug, Default)]
+pub struct NonceTracker {
+    used: HashMap<Vec<u8>, HashSet<Vec<u8>>>,
+    // CTR counter blocks already used with each key, as inclusive ranges
+    counters: HashMap<Vec<u8>, Vec<(u128, u128)>>,
+}
+
+impl NonceTracker {
+    pub fn new() -> NonceTracker {
+        NonceTracker::default()
+    }
+
+    // fails without recording anything if the pair has been seen before
+    pub fn record(&mut self, key: &[u8], nonce: &[u8]) -> Result<(), Error> {
+        let nonces = self.used.entry(key.to_vec()).or_default();
+        if !nonces.insert(nonce.to_vec()) {
+            return Err(Error::NonceReused);
+        }
+        Ok(())
+    }
+
+    // for CTR, where a message uses the counter blocks first, first + 1, ... and
+    // two messages share keystream whenever their ranges overlap, not only when
+    // they start at the same block
+    pub fn record_counters(&mut self, key: &[u8], first: u128, blocks: u128) -> Result<(), Error> {
+        if blocks == 0 {
+            return Ok(());
+        }
+
+        // a range running past the top wraps around to zero
+        let last = first.wrapping_add(blocks - 1);
+        let new_ranges = if last < first {
+            vec![(first, u128::MAX), (0, last)]
+        } else {
+            vec![(first, last)]
+        };
+
+        let ranges = self.counters.entry(key.to_vec()).or_default();
+        let overlaps = ranges
+            .iter()
+            .any(|&(start, end)| new_ranges.iter().any(|&(new_start, new_end)| new_start <= end && start <= new_end));
+        if overlaps {
+            return Err(Error::NonceReused);
+        }
+
+        ranges.extend(new_ranges);
+        Ok(())
+    }
+
+    pub fn has_seen(&self, key: &[u8], nonce: &[u8]) -> bool {
+        self.used.get(key).is_some_and(|nonces| nonces.contains(nonce))
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nonces_are_per_key() {
+        let mut tracker = NonceTracker::new();
+
+        assert_eq!(tracker.record(&[1; 16], &[0; 12]), Ok(()));
+        assert_eq!(tracker.record(&[2; 16], &[0; 12]), Ok(()));
+        assert_eq!(tracker.record(&[1; 16], &[1; 12]), Ok(()));
+        assert_eq!(tracker.record(&[1; 16], &[0; 12]), Err(Error::NonceReused));
+
+        assert!(tracker.has_seen(&[2; 16], &[0; 12]));
+        assert!(!tracker.has_seen(&[2; 16], &[1; 12]));
+    }
+
+    #[test]
+    fn counter_ranges_must_not_overlap() {
+        let mut tracker = NonceTracker::new();
+
+        assert_eq!(tracker.record_counters(&[1; 16], 10, 2), Ok(()));
+        assert_eq!(tracker.record_counters(&[1; 16], 11, 1), Err(Error::NonceReused));
+        assert_eq!(tracker.record_counters(&[1; 16], 5, 6), Err(Error::NonceReused));
+        assert_eq!(tracker.record_counters(&[1; 16], 12, 3), Ok(()));
+        assert_eq!(tracker.record_counters(&[2; 16], 11, 1), Ok(()));
+
+        // wrapping past the top also covers the bottom
+        assert_eq!(tracker.record_counters(&[1; 16], u128::MAX, 2), Ok(()));
+        assert_eq!(tracker.record_counters(&[1; 16], 0, 1), Err(Error::NonceReused));
+    }
+}
@@ -0,0 +1,314 @@
+use super::block::{Aes, BlockCipher};
+use super::{constant_time_eq, Error};
+
+/* ----------- ENCRYPTION AND DECRYPTION ------------ */
+// OCB3 from RFC 7253, returns the ciphertext followed by a tag of tag_length bytes
+pub fn aes_ocb_encrypt(
+    input: Vec<u8>,
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    associated_data: Vec<u8>,
+    tag_length: usize,
+) -> Vec<u8> {
+    let mut ocb = Ocb::new(key, tag_length);
+
+    let (mut cipher_text, tag) = ocb.process(&input, &nonce, &associated_data, true);
+    cipher_text.extend_from_slice(&tag);
+    cipher_text
+}
+
+pub fn aes_ocb_decrypt(
+    input: Vec<u8>,
+    key: Vec<u8>,
+    nonce: Vec<u8>,
+    associated_data: Vec<u8>,
+    tag_length: usize,
+) -> Result<Vec<u8>, Error> {
+    let mut ocb = Ocb::new(key, tag_length);
+
+    if input.len() < tag_length {
+        return Err(Error::InvalidLength);
+    }
+    let (cipher_text, received_tag) = input.split_at(input.len() - tag_length);
+
+    let (plain_text, tag) = ocb.process(cipher_text, &nonce, &associated_data, false);
+
+    if !constant_time_eq(&tag, received_tag) {
+        return Err(Error::AuthenticationFailed);
+    }
+
+    Ok(plain_text)
+}
+
+/* ----------- OCB FUNCTIONS ------------ */
+// blocks are handled as big endian u128 values to keep the offset arithmetic simple
+struct Ocb {
+    cipher: Aes,
+    tag_length: usize,
+    l_star: u128,
+    l_dollar: u128,
+    // L_0, L_1, ... grown as longer messages need them
+    l: Vec<u128>,
+}
+
+impl Ocb {
+    fn new(key: Vec<u8>, tag_length: usize) -> Ocb {
+        if !(1..=16).contains(&tag_length) {
+            panic!("Invalid tag length");
+        }
+
+        let cipher = Aes::new(key);
+
+        let l_star = from_block(&cipher.encrypt_block(vec![0; 16]));
+        let l_dollar = double(l_star);
+
+        Ocb {
+            cipher,
+            tag_length,
+            l_star,
+            l_dollar,
+            l: vec![double(l_dollar)],
+        }
+    }
+
+    fn encrypt_block(&self, block: u128) -> u128 {
+        from_block(&self.cipher.encrypt_block(to_block(block)))
+    }
+
+    fn decrypt_block(&self, block: u128) -> u128 {
+        from_block(&self.cipher.decrypt_block(to_block(block)))
+    }
+
+    // L_{ntz(i)} for block number i, starting from 1
+    fn l_for(&mut self, i: usize) -> u128 {
+        let ntz = i.trailing_zeros() as usize;
+        while self.l.len() <= ntz {
+            let last = *self.l.last().unwrap();
+            self.l.push(double(last));
+        }
+        self.l[ntz]
+    }
+
+    fn initial_offset(&self, nonce: &[u8]) -> u128 {
+        if nonce.is_empty() || nonce.len() > 15 {
+            panic!("Invalid nonce length");
+        }
+
+        // num2str(TAGLEN mod 128, 7) || zeros || 1 || N
+        let mut formatted: u128 = ((self.tag_length as u128 * 8) % 128) << 121;
+        formatted |= 1 << (nonce.len() * 8);
+        for (i, byte) in nonce.iter().rev().enumerate() {
+            formatted |= (*byte as u128) << (i * 8);
+        }
+
+        let bottom = (formatted & 0x3f) as u32;
+        let ktop = self.encrypt_block(formatted & !0x3f);
+        let stretch = (((ktop >> 64) ^ (ktop >> 56)) as u64) as u128;
+
+        if bottom == 0 {
+            ktop
+        } else {
+            (ktop << bottom) | (stretch >> (64 - bottom))
+        }
+    }
+
+    fn hash(&mut self, associated_data: &[u8]) -> u128 {
+        let mut sum = 0;
+        let mut offset = 0;
+
+        let num_blocks = associated_data.len() / 16;
+        for i in 0..num_blocks {
+            offset ^= self.l_for(i + 1);
+            let block = from_block(&associated_data[i * 16..(i + 1) * 16]);
+            sum ^= self.encrypt_block(block ^ offset);
+        }
+
+        let remainder = &associated_data[num_blocks * 16..];
+        if !remainder.is_empty() {
+            offset ^= self.l_star;
+            sum ^= self.encrypt_block(pad_partial(remainder) ^ offset);
+        }
+
+        sum
+    }
+
+    // returns the processed text and the tag
+    fn process(&mut self, input: &[u8], nonce: &[u8], associated_data: &[u8], encrypting: bool) -> (Vec<u8>, Vec<u8>) {
+        let mut offset = self.initial_offset(nonce);
+        let mut checksum = 0;
+        let mut output: Vec<u8> = Vec::new();
+
+        let num_blocks = input.len() / 16;
+        for i in 0..num_blocks {
+            offset ^= self.l_for(i + 1);
+            let block = from_block(&input[i * 16..(i + 1) * 16]);
+
+            let processed = if encrypting {
+                checksum ^= block;
+                self.encrypt_block(block ^ offset) ^ offset
+            } else {
+                let plain = self.decrypt_block(block ^ offset) ^ offset;
+                checksum ^= plain;
+                plain
+            };
+            output.extend_from_slice(&to_block(processed));
+        }
+
+        let remainder = &input[num_blocks * 16..];
+        if !remainder.is_empty() {
+            offset ^= self.l_star;
+            let pad = to_block(self.encrypt_block(offset));
+
+            let processed: Vec<u8> = remainder.iter().zip(pad).map(|(byte, pad)| byte ^ pad).collect();
+            checksum ^= pad_partial(if encrypting { remainder } else { &processed });
+            output.extend_from_slice(&processed);
+        }
+
+        let tag = self.encrypt_block(checksum ^ offset ^ self.l_dollar) ^ self.hash(associated_data);
+
+        let mut tag = to_block(tag);
+        tag.truncate(self.tag_length);
+        (output, tag)
+    }
+}
+
+// multiply by x in GF(2^128) with the OCB reduction polynomial
+fn double(block: u128) -> u128 {
+    let carry = block >> 127;
+    (block << 1) ^ (carry * 0x87)
+}
+
+// partial block followed by a single 1 bit and zeros
+fn pad_partial(bytes: &[u8]) -> u128 {
+    let mut block = bytes.to_vec();
+    block.push(0x80);
+    block.resize(16, 0);
+    from_block(&block)
+}
+
+fn from_block(block: &[u8]) -> u128 {
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(block);
+    u128::from_be_bytes(bytes)
+}
+
+fn to_block(value: u128) -> Vec<u8> {
+    value.to_be_bytes().to_vec()
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_vector(nonce: &str, associated_length: u8, plain_length: u8, expected: &str) {
+        let key: Vec<u8> = (0..16).collect();
+        let nonce = hex::decode(nonce).unwrap();
+        let associated_data: Vec<u8> = (0..associated_length).collect();
+        let plain_text: Vec<u8> = (0..plain_length).collect();
+        let expected = hex::decode(expected).unwrap();
+
+        let encrypted = aes_ocb_encrypt(plain_text.clone(), key.clone(), nonce.clone(), associated_data.clone(), 16);
+        assert_eq!(encrypted, expected);
+
+        let decrypted = aes_ocb_decrypt(encrypted, key, nonce, associated_data, 16);
+        assert_eq!(decrypted, Ok(plain_text));
+    }
+
+    #[test]
+    fn rfc_7253_sample_results() {
+        check_vector("bbaa99887766554433221100", 0, 0, "785407bfffc8ad9edcc5520ac9111ee6");
+        check_vector("bbaa99887766554433221101", 8, 8, "6820b3657b6f615a5725bda0d3b4eb3a257c9af1f8f03009");
+        check_vector("bbaa99887766554433221102", 8, 0, "81017f8203f081277152fade694a0a00");
+        check_vector("bbaa99887766554433221103", 0, 8, "45dd69f8f5aae72414054cd1f35d82760b2cd00d2f99bfa9");
+        check_vector(
+            "bbaa99887766554433221104",
+            16,
+            16,
+            "571d535b60b277188be5147170a9a22c3ad7a4ff3835b8c5701c1ccec8fc3358",
+        );
+        check_vector("bbaa99887766554433221105", 16, 0, "8cf761b6902ef764462ad86498ca6b97");
+        check_vector(
+            "bbaa99887766554433221106",
+            0,
+            16,
+            "5ce88ec2e0692706a915c00aeb8b2396f40e1c743f52436bdf06d8fa1eca343d",
+        );
+        check_vector(
+            "bbaa99887766554433221107",
+            24,
+            24,
+            "1ca2207308c87c010756104d8840ce1952f09673a448a122c92c62241051f57356d7f3c90bb0e07f",
+        );
+        check_vector("bbaa99887766554433221108", 24, 0, "6dc225a071fc1b9f7c69f93b0f1e10de");
+        check_vector(
+            "bbaa99887766554433221109",
+            0,
+            24,
+            "221bd0de7fa6fe993eccd769460a0af2d6cded0c395b1c3ce725f32494b9f914d85c0b1eb38357ff",
+        );
+    }
+
+    #[test]
+    fn rfc_7253_96_bit_tag() {
+        let key: Vec<u8> = (0..16).rev().collect();
+        let nonce = hex::decode("bbaa9988776655443322110d").unwrap();
+        let data: Vec<u8> = (0..40).collect();
+        let expected = hex::decode(
+            "1792a4e31e0755fb03e31b22116e6c2ddf9efd6e33d536f1a0124b0a55bae884\
+             ed93481529c76b6ad0c515f4d1cdd4fdac4f02aa",
+        )
+        .unwrap();
+
+        let encrypted = aes_ocb_encrypt(data.clone(), key.clone(), nonce.clone(), data.clone(), 12);
+        assert_eq!(encrypted, expected);
+        assert_eq!(aes_ocb_decrypt(encrypted, key, nonce, data.clone(), 12), Ok(data));
+    }
+
+    // RFC 7253 appendix A
+    fn iterated_result(key_length: usize, tag_length: usize) -> Vec<u8> {
+        let mut key = vec![0; key_length / 8];
+        *key.last_mut().unwrap() = (tag_length * 8) as u8;
+
+        let nonce = |n: u32| {
+            let mut nonce = vec![0; 8];
+            nonce.extend_from_slice(&n.to_be_bytes());
+            nonce
+        };
+
+        let mut cipher_text: Vec<u8> = Vec::new();
+        for i in 0..128 {
+            let s = vec![0; i as usize];
+            cipher_text.append(&mut aes_ocb_encrypt(s.clone(), key.clone(), nonce(3 * i + 1), s.clone(), tag_length));
+            cipher_text.append(&mut aes_ocb_encrypt(s.clone(), key.clone(), nonce(3 * i + 2), Vec::new(), tag_length));
+            cipher_text.append(&mut aes_ocb_encrypt(Vec::new(), key.clone(), nonce(3 * i + 3), s, tag_length));
+        }
+
+        aes_ocb_encrypt(Vec::new(), key, nonce(385), cipher_text, tag_length)
+    }
+
+    #[test]
+    fn rfc_7253_iterated() {
+        assert_eq!(iterated_result(128, 16), hex::decode("67e944d23256c5e0b6c61fa22fdf1ea2").unwrap());
+        assert_eq!(iterated_result(192, 16), hex::decode("f673f2c3e7174aae7bae986ca9f29e17").unwrap());
+        assert_eq!(iterated_result(256, 16), hex::decode("d90eb8e9c977c88b79dd793d7ffa161c").unwrap());
+        assert_eq!(iterated_result(128, 12), hex::decode("77a3d8e73589158d25d01209").unwrap());
+        assert_eq!(iterated_result(128, 8), hex::decode("192c9b7bd90ba06a").unwrap());
+    }
+
+    #[test]
+    fn decrypt_rejects_tampered() {
+        let key = crate::key_gen(192);
+        let nonce = vec![0x07; 12];
+
+        let mut encrypted = aes_ocb_encrypt(b"message for a partner".to_vec(), key.clone(), nonce.clone(), Vec::new(), 16);
+        encrypted[18] ^= 0x01;
+
+        assert_eq!(
+            aes_ocb_decrypt(encrypted, key, nonce, Vec::new(), 16),
+            Err(Error::AuthenticationFailed)
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use super::block::{Aes, BlockCipher};
+
+/* ----------- ONE SHOT ------------ */
+pub fn aes_ofb_encrypt(input: Vec<u8>, key: Vec<u8>, iv: Vec<u8>) -> Vec<u8> {
+    Ofb::new(key, iv).apply_keystream(&input)
+}
+
+// OFB decryption is the same operation as encryption
+pub fn aes_ofb_decrypt(input: Vec<u8>, key: Vec<u8>, iv: Vec<u8>) -> Vec<u8> {
+    Ofb::new(key, iv).apply_keystream(&input)
+}
+
+/* ----------- STREAMING ------------ */
+pub struct Ofb {
+    cipher: Aes,
+    // the last output block, which is also the next cipher input
+    register: Vec<u8>,
+    position: usize,
+}
+
+impl Ofb {
+    pub fn new(key: Vec<u8>, iv: Vec<u8>) -> Ofb {
+        if iv.len() != 16 {
+            panic!("Invalid IV length");
+        }
+
+        Ofb {
+            cipher: Aes::new(key),
+            register: iv,
+            position: 0,
+        }
+    }
+
+    pub fn apply_keystream(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut output: Vec<u8> = Vec::new();
+        for byte in input {
+            if self.position == 0 {
+                self.register = self.cipher.encrypt_block(self.register.clone());
+            }
+            output.push(byte ^ self.register[self.position]);
+            self.position = (self.position + 1) % 16;
+        }
+        output
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN_TEXT: &str = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51\
+                              30c81c46a35ce411e5fbc1191a0a52eff69f2445df4f9b17ad2b417be66c3710";
+
+    fn check_vector(key: &str, expected: &str) {
+        let key = hex::decode(key).unwrap();
+        let iv: Vec<u8> = (0..16).collect();
+        let plain_text = hex::decode(PLAIN_TEXT).unwrap();
+        let expected = hex::decode(expected).unwrap();
+
+        let encrypted = aes_ofb_encrypt(plain_text.clone(), key.clone(), iv.clone());
+        assert_eq!(encrypted, expected);
+
+        let decrypted = aes_ofb_decrypt(encrypted, key, iv);
+        assert_eq!(decrypted, plain_text);
+    }
+
+    #[test]
+    fn ofb_128_sp800_38a() {
+        check_vector(
+            "2b7e151628aed2a6abf7158809cf4f3c",
+            "3b3fd92eb72dad20333449f8e83cfb4a7789508d16918f03f53c52dac54ed825\
+             9740051e9c5fecf64344f7a82260edcc304c6528f659c77866a510d9c1d6ae5e",
+        );
+    }
+
+    #[test]
+    fn ofb_192_sp800_38a() {
+        check_vector(
+            "8e73b0f7da0e6452c810f32b809079e562f8ead2522c6b7b",
+            "cdc80d6fddf18cab34c25909c99a4174fcc28b8d4c63837c09e81700c1100401\
+             8d9a9aeac0f6596f559c6d4daf59a5f26d9f200857ca6c3e9cac524bd9acc92a",
+        );
+    }
+
+    #[test]
+    fn ofb_256_sp800_38a() {
+        check_vector(
+            "603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4",
+            "dc7e84bfda79164b7ecd8486985d38604febdc6740d20b3ac88f6ad82a4fb08d\
+             71ab47a086e86eedf39d1c5bba97c4080126141d67f37be8538f5a8be740e484",
+        );
+    }
+
+    #[test]
+    fn ofb_streaming() {
+        let key = crate::key_gen(128);
+        let iv = crate::iv_gen();
+        let plain_text = hex::decode(PLAIN_TEXT).unwrap();
+
+        let expected = aes_ofb_encrypt(plain_text.clone(), key.clone(), iv.clone());
+
+        let mut ofb = Ofb::new(key, iv);
+        let mut encrypted: Vec<u8> = Vec::new();
+        for piece in plain_text.chunks(5) {
+            encrypted.append(&mut ofb.apply_keystream(piece));
+        }
+        assert_eq!(encrypted, expected);
+    }
+}
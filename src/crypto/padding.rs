@@ -0,0 +1,268 @@
+use rand::Rng;
+
+use super::Error;
+
+// Pads input up to a multiple of the block size and strips it off again.
+// unpad never panics, malformed padding is reported as Error::InvalidPadding.
+pub trait Padding {
+    fn pad(&self, input: Vec<u8>, block_size: usize) -> Vec<u8>;
+    fn unpad(&self, input: Vec<u8>, block_size: usize) -> Result<Vec<u8>, Error>;
+}
+
+// every padding byte holds the padding length
+pub struct Pkcs7;
+// zeros followed by a final byte holding the padding length
+pub struct AnsiX923;
+// a single 0x80 byte followed by zeros
+pub struct Iso7816;
+// random bytes followed by a final byte holding the padding length
+pub struct Iso10126;
+// zeros, only when needed, can't tell padding from trailing zeros in the data
+pub struct ZeroPadding;
+
+/* ----------- IMPLEMENTATIONS ------------ */
+impl Padding for Pkcs7 {
+    fn pad(&self, mut input: Vec<u8>, block_size: usize) -> Vec<u8> {
+        let padding = padding_length(input.len(), block_size);
+        input.resize(input.len() + padding, padding as u8);
+        input
+    }
+
+    fn unpad(&self, mut input: Vec<u8>, block_size: usize) -> Result<Vec<u8>, Error> {
+        let padding = length_byte(&input, block_size)?;
+        let padding_start = input.len() - padding;
+
+        if input[padding_start..].iter().any(|&byte| byte as usize != padding) {
+            return Err(Error::InvalidPadding);
+        }
+
+        input.truncate(padding_start);
+        Ok(input)
+    }
+}
+
+impl Padding for AnsiX923 {
+    fn pad(&self, mut input: Vec<u8>, block_size: usize) -> Vec<u8> {
+        let padding = padding_length(input.len(), block_size);
+        input.resize(input.len() + padding - 1, 0);
+        input.push(padding as u8);
+        input
+    }
+
+    fn unpad(&self, mut input: Vec<u8>, block_size: usize) -> Result<Vec<u8>, Error> {
+        let padding = length_byte(&input, block_size)?;
+        let padding_start = input.len() - padding;
+
+        if input[padding_start..input.len() - 1].iter().any(|&byte| byte != 0) {
+            return Err(Error::InvalidPadding);
+        }
+
+        input.truncate(padding_start);
+        Ok(input)
+    }
+}
+
+impl Padding for Iso7816 {
+    fn pad(&self, mut input: Vec<u8>, block_size: usize) -> Vec<u8> {
+        let padding = padding_length(input.len(), block_size);
+        input.push(0x80);
+        input.resize(input.len() + padding - 1, 0);
+        input
+    }
+
+    fn unpad(&self, mut input: Vec<u8>, block_size: usize) -> Result<Vec<u8>, Error> {
+        check_length(&input, block_size)?;
+
+        // the marker has to be inside the last block
+        let last_block = input.len() - block_size;
+        let marker = match input[last_block..].iter().rposition(|&byte| byte != 0) {
+            Some(position) => last_block + position,
+            None => return Err(Error::InvalidPadding),
+        };
+
+        if input[marker] != 0x80 {
+            return Err(Error::InvalidPadding);
+        }
+
+        input.truncate(marker);
+        Ok(input)
+    }
+}
+
+impl Padding for Iso10126 {
+    fn pad(&self, mut input: Vec<u8>, block_size: usize) -> Vec<u8> {
+        let padding = padding_length(input.len(), block_size);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..padding - 1 {
+            input.push(rng.gen());
+        }
+        input.push(padding as u8);
+        input
+    }
+
+    fn unpad(&self, mut input: Vec<u8>, block_size: usize) -> Result<Vec<u8>, Error> {
+        // the random bytes can't be checked, only the length
+        let padding = length_byte(&input, block_size)?;
+        input.truncate(input.len() - padding);
+        Ok(input)
+    }
+}
+
+impl Padding for ZeroPadding {
+    fn pad(&self, mut input: Vec<u8>, block_size: usize) -> Vec<u8> {
+        if !input.len().is_multiple_of(block_size) {
+            input.resize(input.len().div_ceil(block_size) * block_size, 0);
+        }
+        input
+    }
+
+    fn unpad(&self, mut input: Vec<u8>, block_size: usize) -> Result<Vec<u8>, Error> {
+        if !input.len().is_multiple_of(block_size) {
+            return Err(Error::InvalidPadding);
+        }
+
+        // the whole run of trailing zeros goes, so any zero bytes the plaintext
+        // itself ended with are lost along with the padding
+        let end = input.iter().rposition(|&byte| byte != 0).map_or(0, |position| position + 1);
+        input.truncate(end);
+        Ok(input)
+    }
+}
+
+/* ----------- HELPERS ------------ */
+// always between 1 and block_size, a full block is added to aligned input
+fn padding_length(length: usize, block_size: usize) -> usize {
+    if block_size == 0 || block_size > 255 {
+        panic!("Invalid block size");
+    }
+    block_size - (length % block_size)
+}
+
+fn check_length(input: &[u8], block_size: usize) -> Result<(), Error> {
+    if input.is_empty() || block_size == 0 || !input.len().is_multiple_of(block_size) {
+        return Err(Error::InvalidPadding);
+    }
+    Ok(())
+}
+
+// reads the final length byte and checks it is between 1 and block_size
+fn length_byte(input: &[u8], block_size: usize) -> Result<usize, Error> {
+    check_length(input, block_size)?;
+
+    let padding = *input.last().unwrap() as usize;
+    if padding == 0 || padding > block_size {
+        return Err(Error::InvalidPadding);
+    }
+    Ok(padding)
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(padding: &dyn Padding) {
+        for length in 0..40 {
+            let input: Vec<u8> = (1..=length).collect();
+            let padded = padding.pad(input.clone(), 16);
+
+            assert_eq!(padded.len() % 16, 0);
+            assert_eq!(padding.unpad(padded, 16), Ok(input));
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(&Pkcs7);
+        round_trip(&AnsiX923);
+        round_trip(&Iso7816);
+        round_trip(&Iso10126);
+        round_trip(&ZeroPadding);
+    }
+
+    #[test]
+    fn pad_values() {
+        let input = vec![0xaa; 13];
+
+        assert_eq!(Pkcs7.pad(input.clone(), 16)[13..], [0x03, 0x03, 0x03]);
+        assert_eq!(AnsiX923.pad(input.clone(), 16)[13..], [0x00, 0x00, 0x03]);
+        assert_eq!(Iso7816.pad(input.clone(), 16)[13..], [0x80, 0x00, 0x00]);
+        assert_eq!(Iso10126.pad(input.clone(), 16)[15], 0x03);
+        assert_eq!(ZeroPadding.pad(input.clone(), 16)[13..], [0x00, 0x00, 0x00]);
+
+        assert_eq!(Pkcs7.pad(vec![0xaa; 16], 16)[16..], [0x10; 16]);
+        assert_eq!(ZeroPadding.pad(vec![0xaa; 16], 16).len(), 16);
+    }
+
+    #[test]
+    fn pkcs7_rejects_malformed() {
+        let mut block = vec![0xaa; 16];
+
+        block[15] = 0x00;
+        assert_eq!(Pkcs7.unpad(block.clone(), 16), Err(Error::InvalidPadding));
+
+        block[15] = 0x11;
+        assert_eq!(Pkcs7.unpad(block.clone(), 16), Err(Error::InvalidPadding));
+
+        block[15] = 0x02;
+        assert_eq!(Pkcs7.unpad(block.clone(), 16), Err(Error::InvalidPadding));
+
+        assert_eq!(Pkcs7.unpad(Vec::new(), 16), Err(Error::InvalidPadding));
+        assert_eq!(Pkcs7.unpad(vec![0x01; 15], 16), Err(Error::InvalidPadding));
+    }
+
+    #[test]
+    fn ansi_x923_rejects_malformed() {
+        let mut block = vec![0xaa; 16];
+
+        block[15] = 0x00;
+        assert_eq!(AnsiX923.unpad(block.clone(), 16), Err(Error::InvalidPadding));
+
+        block[15] = 0x20;
+        assert_eq!(AnsiX923.unpad(block.clone(), 16), Err(Error::InvalidPadding));
+
+        // the bytes before the length have to be zero
+        block[15] = 0x03;
+        assert_eq!(AnsiX923.unpad(block, 16), Err(Error::InvalidPadding));
+    }
+
+    #[test]
+    fn iso_7816_rejects_malformed() {
+        assert_eq!(Iso7816.unpad(vec![0x00; 16], 16), Err(Error::InvalidPadding));
+
+        let mut block = vec![0x00; 16];
+        block[10] = 0x81;
+        assert_eq!(Iso7816.unpad(block, 16), Err(Error::InvalidPadding));
+    }
+
+    #[test]
+    fn iso_10126_rejects_malformed() {
+        let mut block = vec![0xaa; 16];
+
+        block[15] = 0x00;
+        assert_eq!(Iso10126.unpad(block.clone(), 16), Err(Error::InvalidPadding));
+
+        block[15] = 0xff;
+        assert_eq!(Iso10126.unpad(block, 16), Err(Error::InvalidPadding));
+    }
+
+    #[test]
+    fn zero_padding_rejects_malformed() {
+        assert_eq!(ZeroPadding.unpad(vec![0x00; 17], 16), Err(Error::InvalidPadding));
+    }
+
+    #[test]
+    fn zero_padding_strips_every_trailing_zero() {
+        assert_eq!(ZeroPadding.unpad(vec![0x00; 16], 16), Ok(Vec::new()));
+
+        let mut input = vec![0xaa; 20];
+        input.resize(32, 0x00);
+        assert_eq!(ZeroPadding.unpad(input, 16), Ok(vec![0xaa; 20]));
+
+        // trailing zeros in the data can't be told apart from padding
+        assert_eq!(ZeroPadding.unpad(ZeroPadding.pad(vec![0xaa, 0x00], 16), 16), Ok(vec![0xaa]));
+    }
+}
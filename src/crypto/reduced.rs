@@ -0,0 +1,124 @@
+use super::aes;
+
+// Reduced-round AES for cryptanalysis coursework.
+//
+// This is deliberately not a BlockCipher, so it can't be handed to any of the
+// modes or to encrypt_with_mode and end up protecting real data.
+pub struct ReducedAes {
+    num_rounds: u8,
+    final_mix_columns: bool,
+    key_schedule: Vec<Vec<u8>>,
+}
+
+impl ReducedAes {
+    // num_rounds can be anything from 1 up to the full count for the key size,
+    // final_mix_columns keeps MixColumns in the last round
+    pub fn new(key: Vec<u8>, num_rounds: u8, final_mix_columns: bool) -> ReducedAes {
+        if ![16, 24, 32].contains(&key.len()) {
+            panic!("Invalid key length");
+        }
+
+        let full_rounds = aes::num_rounds(key.len() / 4);
+        if num_rounds == 0 || num_rounds > full_rounds {
+            panic!("Invalid number of rounds");
+        }
+
+        ReducedAes {
+            num_rounds,
+            final_mix_columns,
+            key_schedule: aes::key_expansion(key),
+        }
+    }
+
+    pub fn num_rounds(&self) -> u8 {
+        self.num_rounds
+    }
+
+    pub fn final_mix_columns(&self) -> bool {
+        self.final_mix_columns
+    }
+
+    // the four words used in the given round, 0 is the whitening key
+    pub fn round_key(&self, round: u8) -> Vec<u8> {
+        if round > self.num_rounds {
+            panic!("Invalid round");
+        }
+        let round = round as usize;
+        self.key_schedule[round * 4..round * 4 + 4].concat()
+    }
+
+    pub fn encrypt_block(&self, block: Vec<u8>) -> Vec<u8> {
+        if block.len() != 16 {
+            panic!("Invalid block length");
+        }
+        aes::reduced_cipher(block, self.num_rounds, &self.key_schedule, self.final_mix_columns)
+    }
+
+    pub fn decrypt_block(&self, block: Vec<u8>) -> Vec<u8> {
+        if block.len() != 16 {
+            panic!("Invalid block length");
+        }
+        aes::reduced_inv_cipher(block, self.num_rounds, &self.key_schedule, self.final_mix_columns)
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // FIPS-197 appendix B
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const INPUT: &str = "3243f6a8885a308d313198a2e0370734";
+
+    fn encrypt(num_rounds: u8, final_mix_columns: bool) -> Vec<u8> {
+        let reduced = ReducedAes::new(hex::decode(KEY).unwrap(), num_rounds, final_mix_columns);
+        let output = reduced.encrypt_block(hex::decode(INPUT).unwrap());
+        assert_eq!(reduced.decrypt_block(output.clone()), hex::decode(INPUT).unwrap());
+        output
+    }
+
+    #[test]
+    fn full_rounds_match_aes() {
+        assert_eq!(encrypt(10, false), hex::decode("3925841d02dc09fbdc118597196a0b32").unwrap());
+    }
+
+    #[test]
+    fn intermediate_rounds_match_fips_197() {
+        // with MixColumns kept the output is the start of the next round
+        assert_eq!(encrypt(1, true), hex::decode("a49c7ff2689f352b6b5bea43026a5049").unwrap());
+        assert_eq!(encrypt(2, true), hex::decode("aa8f5f0361dde3ef82d24ad26832469a").unwrap());
+    }
+
+    #[test]
+    fn every_round_count_round_trips() {
+        for key_size in [128, 192, 256] {
+            let key = crate::key_gen(key_size);
+            let full_rounds = aes::num_rounds(key.len() / 4);
+
+            for num_rounds in 1..=full_rounds {
+                for final_mix_columns in [false, true] {
+                    let reduced = ReducedAes::new(key.clone(), num_rounds, final_mix_columns);
+                    let block: Vec<u8> = (0..16).collect();
+                    let encrypted = reduced.encrypt_block(block.clone());
+                    assert_eq!(reduced.decrypt_block(encrypted), block);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_keys() {
+        let reduced = ReducedAes::new(hex::decode(KEY).unwrap(), 4, false);
+        assert_eq!(reduced.round_key(0), hex::decode(KEY).unwrap());
+        assert_eq!(reduced.round_key(1), hex::decode("a0fafe1788542cb123a339392a6c7605").unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid number of rounds")]
+    fn rejects_too_many_rounds() {
+        ReducedAes::new(vec![0; 16], 11, false);
+    }
+}
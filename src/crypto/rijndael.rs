@@ -0,0 +1,131 @@
+use super::aes;
+use super::block::BlockCipher;
+
+// The full Rijndael family, AES is the subset with 128 bit blocks.
+// Keys and blocks can each be 128, 160, 192, 224 or 256 bits.
+#[derive(Clone)]
+pub struct Rijndael {
+    block_size: usize,
+    num_rounds: u8,
+    key_schedule: Vec<Vec<u8>>,
+}
+
+impl Rijndael {
+    pub fn new(key: Vec<u8>, block_size: usize) -> Rijndael {
+        if !key.len().is_multiple_of(4) || !(16..=32).contains(&key.len()) {
+            panic!("Invalid key length");
+        }
+        if !block_size.is_multiple_of(4) || !(16..=32).contains(&block_size) {
+            panic!("Invalid block size");
+        }
+
+        let n_b = block_size / 4;
+        let num_rounds = aes::rijndael_num_rounds(key.len() / 4, n_b);
+
+        Rijndael {
+            block_size,
+            num_rounds,
+            key_schedule: aes::rijndael_key_expansion(key, n_b),
+        }
+    }
+
+    pub fn num_rounds(&self) -> u8 {
+        self.num_rounds
+    }
+}
+
+impl BlockCipher for Rijndael {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn encrypt_block(&self, block: Vec<u8>) -> Vec<u8> {
+        if block.len() != self.block_size {
+            panic!("Invalid block length");
+        }
+        aes::cipher(block, self.num_rounds, &self.key_schedule)
+    }
+
+    fn decrypt_block(&self, block: Vec<u8>) -> Vec<u8> {
+        if block.len() != self.block_size {
+            panic!("Invalid block length");
+        }
+        aes::inv_cipher(block, self.num_rounds, &self.key_schedule)
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the example vectors from the Rijndael submission use prefixes of these
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfe";
+    const PLAIN_TEXT: &str = "3243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c8";
+
+    fn check_vector(block_bits: usize, key_bits: usize, expected: &str) {
+        let key = hex::decode(KEY).unwrap()[..key_bits / 8].to_vec();
+        let plain_text = hex::decode(PLAIN_TEXT).unwrap()[..block_bits / 8].to_vec();
+        let expected = hex::decode(expected).unwrap();
+
+        let rijndael = Rijndael::new(key, block_bits / 8);
+        let encrypted = rijndael.encrypt_block(plain_text.clone());
+        assert_eq!(encrypted, expected, "block {} key {}", block_bits, key_bits);
+        assert_eq!(rijndael.decrypt_block(encrypted), plain_text);
+    }
+
+    #[test]
+    fn block_128() {
+        check_vector(128, 128, "3925841d02dc09fbdc118597196a0b32");
+        check_vector(128, 160, "231d844639b31b412211cfe93712b880");
+        check_vector(128, 192, "f9fb29aefc384a250340d833b87ebc00");
+        check_vector(128, 224, "8faa8fe4dee9eb17caa4797502fc9d3f");
+        check_vector(128, 256, "1a6e6c2c662e7da6501ffb62bc9e93f3");
+    }
+
+    #[test]
+    fn block_160() {
+        check_vector(160, 128, "16e73aec921314c29df905432bc8968ab64b1f51");
+        check_vector(160, 160, "0553eb691670dd8a5a5b5addf1aa7450f7a0e587");
+        check_vector(160, 192, "73cd6f3423036790463aa9e19cfcde894ea16623");
+        check_vector(160, 224, "601b5dcd1cf4ece954c740445340bf0afdc048df");
+        check_vector(160, 256, "579e930b36c1529aa3e86628bacfe146942882cf");
+    }
+
+    #[test]
+    fn block_192() {
+        check_vector(192, 128, "b24d275489e82bb8f7375e0d5fcdb1f481757c538b65148a");
+        check_vector(192, 160, "738dae25620d3d3beff4a037a04290d73eb33521a63ea568");
+        check_vector(192, 192, "725ae43b5f3161de806a7c93e0bca93c967ec1ae1b71e1cf");
+        check_vector(192, 224, "bbfc14180afbf6a36382a061843f0b63e769acdc98769130");
+        check_vector(192, 256, "0ebacf199e3315c2e34b24fcc7c46ef4388aa475d66c194c");
+    }
+
+    #[test]
+    fn block_224() {
+        check_vector(224, 128, "b0a8f78f6b3c66213f792ffd2a61631f79331407a5e5c8d3793aceb1");
+        check_vector(224, 160, "08b99944edfce33a2acb131183ab0168446b2d15e958480010f545e3");
+        check_vector(224, 192, "be4c597d8f7efe22a2f7e5b1938e2564d452a5bfe72399c7af1101e2");
+        check_vector(224, 224, "ef529598ecbce297811b49bbed2c33bbe1241d6e1a833dbe119569e8");
+        check_vector(224, 256, "02fafc200176ed05deb8edb82a3555b0b10d47a388dfd59cab2f6c11");
+    }
+
+    #[test]
+    fn block_256() {
+        check_vector(256, 128, "7d15479076b69a46ffb3b3beae97ad8313f622f67fedb487de9f06b9ed9c8f19");
+        check_vector(256, 160, "514f93fb296b5ad16aa7df8b577abcbd484decacccc7fb1f18dc567309ceeffd");
+        check_vector(256, 192, "5d7101727bb25781bf6715b0e6955282b9610e23a43c2eb062699f0ebf5887b2");
+        check_vector(256, 224, "d56c5a63627432579e1dd308b2c8f157b40a4bfb56fea1377b25d3ed3d6dbf80");
+        check_vector(256, 256, "a49406115dfb30a40418aafa4869b7c6a886ff31602a7dd19c889dc64f7e4e7a");
+    }
+
+    #[test]
+    fn round_counts() {
+        assert_eq!(Rijndael::new(vec![0; 16], 16).num_rounds(), 10);
+        assert_eq!(Rijndael::new(vec![0; 16], 32).num_rounds(), 14);
+        assert_eq!(Rijndael::new(vec![0; 20], 24).num_rounds(), 12);
+        assert_eq!(Rijndael::new(vec![0; 28], 20).num_rounds(), 13);
+    }
+}
@@ -0,0 +1,186 @@
+use super::gf256::{self, Gf256};
+
+// The AES S-box worked out instead of looked up. A byte is replaced by its
+// multiplicative inverse in GF(2^8), with 0 kept as 0, and then by an affine
+// map over GF(2): each bit i becomes
+//
+//   b[i] ^ b[i+4] ^ b[i+5] ^ b[i+6] ^ b[i+7] ^ c[i]   (indices mod 8, c = 0x63)
+//
+// which is the byte xored with its rotations left by 1 to 4 bits, then 0x63.
+// Everything here is const, aes::SBOX and aes::INV_SBOX are built from it at
+// compile time.
+
+pub const AFFINE_CONSTANT: u8 = 0x63;
+
+// the constant of the inverse affine map
+pub const INV_AFFINE_CONSTANT: u8 = 0x05;
+
+pub const fn affine(byte: u8) -> u8 {
+    byte ^ byte.rotate_left(1) ^ byte.rotate_left(2) ^ byte.rotate_left(3) ^ byte.rotate_left(4) ^ AFFINE_CONSTANT
+}
+
+pub const fn inv_affine(byte: u8) -> u8 {
+    byte.rotate_left(1) ^ byte.rotate_left(3) ^ byte.rotate_left(6) ^ INV_AFFINE_CONSTANT
+}
+
+pub const fn s_box(byte: u8) -> u8 {
+    affine(gf256::invert(byte, gf256::AES_POLYNOMIAL))
+}
+
+pub const fn inv_s_box(byte: u8) -> u8 {
+    gf256::invert(inv_affine(byte), gf256::AES_POLYNOMIAL)
+}
+
+pub const fn generate_s_box() -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        table[byte] = s_box(byte as u8);
+        byte += 1;
+    }
+    table
+}
+
+pub const fn generate_inv_s_box() -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        table[byte] = inv_s_box(byte as u8);
+        byte += 1;
+    }
+    table
+}
+
+/* ----------- EXPLANATION ------------ */
+// every intermediate value of substituting one byte
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SBoxStep {
+    pub input: u8,
+    pub inverse: u8,
+    // the inverse rotated left by 1, 2, 3 and 4 bits
+    pub rotations: [u8; 4],
+    // the inverse xored with its rotations, before adding the constant
+    pub linear: u8,
+    pub output: u8,
+}
+
+pub fn explain(byte: u8) -> SBoxStep {
+    let inverse = Gf256::new(byte).inverse().value();
+    let rotations = [1, 2, 3, 4].map(|bits| inverse.rotate_left(bits));
+    let linear = rotations.iter().fold(inverse, |acc, rotation| acc ^ rotation);
+
+    SBoxStep {
+        input: byte,
+        inverse,
+        rotations,
+        linear,
+        output: linear ^ AFFINE_CONSTANT,
+    }
+}
+
+impl SBoxStep {
+    // a few lines of text walking through the substitution
+    pub fn render(&self) -> Vec<String> {
+        let input = Gf256::new(self.input);
+        let inverse_line = match self.input {
+            0 => String::from("inverse      00 has no inverse, it stays 00"),
+            _ => format!(
+                "inverse      {:02x} = {}, as {:02x} * {:02x} = 01",
+                self.inverse,
+                Gf256::new(self.inverse).as_polynomial(),
+                self.input,
+                self.inverse
+            ),
+        };
+
+        vec![
+            format!("input        {:02x} = {}", self.input, input.as_polynomial()),
+            inverse_line,
+            format!("             {:08b}", self.inverse),
+            format!(
+                "rotations    {:08b} {:08b} {:08b} {:08b}",
+                self.rotations[0], self.rotations[1], self.rotations[2], self.rotations[3]
+            ),
+            format!("xor of all   {:08b} = {:02x}", self.linear, self.linear),
+            format!("xor 63       {:08b} = {:02x}", self.output, self.output),
+        ]
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::aes;
+
+    // FIPS-197 figures 7 and 14, written out
+    const SBOX: [u8; 256] = [
+        0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+        0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+        0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+        0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+        0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+        0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+        0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+        0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+        0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+        0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+        0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+        0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+        0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+        0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+        0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+        0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+    ];
+
+    const INV_SBOX: [u8; 256] = [
+        0x52, 0x09, 0x6a, 0xd5, 0x30, 0x36, 0xa5, 0x38, 0xbf, 0x40, 0xa3, 0x9e, 0x81, 0xf3, 0xd7, 0xfb,
+        0x7c, 0xe3, 0x39, 0x82, 0x9b, 0x2f, 0xff, 0x87, 0x34, 0x8e, 0x43, 0x44, 0xc4, 0xde, 0xe9, 0xcb,
+        0x54, 0x7b, 0x94, 0x32, 0xa6, 0xc2, 0x23, 0x3d, 0xee, 0x4c, 0x95, 0x0b, 0x42, 0xfa, 0xc3, 0x4e,
+        0x08, 0x2e, 0xa1, 0x66, 0x28, 0xd9, 0x24, 0xb2, 0x76, 0x5b, 0xa2, 0x49, 0x6d, 0x8b, 0xd1, 0x25,
+        0x72, 0xf8, 0xf6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xd4, 0xa4, 0x5c, 0xcc, 0x5d, 0x65, 0xb6, 0x92,
+        0x6c, 0x70, 0x48, 0x50, 0xfd, 0xed, 0xb9, 0xda, 0x5e, 0x15, 0x46, 0x57, 0xa7, 0x8d, 0x9d, 0x84,
+        0x90, 0xd8, 0xab, 0x00, 0x8c, 0xbc, 0xd3, 0x0a, 0xf7, 0xe4, 0x58, 0x05, 0xb8, 0xb3, 0x45, 0x06,
+        0xd0, 0x2c, 0x1e, 0x8f, 0xca, 0x3f, 0x0f, 0x02, 0xc1, 0xaf, 0xbd, 0x03, 0x01, 0x13, 0x8a, 0x6b,
+        0x3a, 0x91, 0x11, 0x41, 0x4f, 0x67, 0xdc, 0xea, 0x97, 0xf2, 0xcf, 0xce, 0xf0, 0xb4, 0xe6, 0x73,
+        0x96, 0xac, 0x74, 0x22, 0xe7, 0xad, 0x35, 0x85, 0xe2, 0xf9, 0x37, 0xe8, 0x1c, 0x75, 0xdf, 0x6e,
+        0x47, 0xf1, 0x1a, 0x71, 0x1d, 0x29, 0xc5, 0x89, 0x6f, 0xb7, 0x62, 0x0e, 0xaa, 0x18, 0xbe, 0x1b,
+        0xfc, 0x56, 0x3e, 0x4b, 0xc6, 0xd2, 0x79, 0x20, 0x9a, 0xdb, 0xc0, 0xfe, 0x78, 0xcd, 0x5a, 0xf4,
+        0x1f, 0xdd, 0xa8, 0x33, 0x88, 0x07, 0xc7, 0x31, 0xb1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xec, 0x5f,
+        0x60, 0x51, 0x7f, 0xa9, 0x19, 0xb5, 0x4a, 0x0d, 0x2d, 0xe5, 0x7a, 0x9f, 0x93, 0xc9, 0x9c, 0xef,
+        0xa0, 0xe0, 0x3b, 0x4d, 0xae, 0x2a, 0xf5, 0xb0, 0xc8, 0xeb, 0xbb, 0x3c, 0x83, 0x53, 0x99, 0x61,
+        0x17, 0x2b, 0x04, 0x7e, 0xba, 0x77, 0xd6, 0x26, 0xe1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0c, 0x7d,
+    ];
+
+    #[test]
+    fn matches_tables() {
+        assert_eq!(generate_s_box(), SBOX);
+        assert_eq!(generate_inv_s_box(), INV_SBOX);
+        assert_eq!(aes::SBOX, SBOX);
+        assert_eq!(aes::INV_SBOX, INV_SBOX);
+        for byte in 0..=255u8 {
+            assert_eq!(inv_affine(affine(byte)), byte);
+            assert_eq!(Gf256::new(byte).inverse().value(), gf256::invert(byte, gf256::AES_POLYNOMIAL));
+        }
+    }
+
+    #[test]
+    fn explains_fips_197_example() {
+        // FIPS-197 section 5.1.1, {53} goes to {ed}
+        let step = explain(0x53);
+        assert_eq!(step.inverse, 0xca);
+        assert_eq!(step.linear, 0x8e);
+        assert_eq!(step.output, 0xed);
+
+        let lines = step.render();
+        assert_eq!(lines[0], "input        53 = x^6 + x^4 + x + 1");
+        assert_eq!(lines[1], "inverse      ca = x^7 + x^6 + x^3 + x, as 53 * ca = 01");
+        assert_eq!(lines[5], "xor 63       11101101 = ed");
+
+        let zero = explain(0);
+        assert_eq!(zero.output, 0x63);
+        assert!(zero.render()[1].contains("no inverse"));
+    }
+}
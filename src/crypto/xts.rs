@@ -0,0 +1,276 @@
+use super::block::{Aes, BlockCipher};
+use super::xor;
+
+/* ----------- SECTOR ENCRYPTION AND DECRYPTION ------------ */
+// key is Key1 || Key2, 32 bytes for XTS-AES-128 and 64 bytes for XTS-AES-256
+pub fn xts_encrypt_sector(data: Vec<u8>, key: Vec<u8>, sector: u128) -> Vec<u8> {
+    let keys = XtsKeys::new(key);
+    keys.encrypt_sector(&data, sector)
+}
+
+pub fn xts_decrypt_sector(data: Vec<u8>, key: Vec<u8>, sector: u128) -> Vec<u8> {
+    let keys = XtsKeys::new(key);
+    keys.decrypt_sector(&data, sector)
+}
+
+/* ----------- IMAGE ENCRYPTION AND DECRYPTION ------------ */
+// splits the image into sector_size chunks numbered from first_sector,
+// the last sector may be shorter but must still hold at least one block
+pub fn xts_encrypt(image: Vec<u8>, key: Vec<u8>, sector_size: usize, first_sector: u128) -> Vec<u8> {
+    let keys = XtsKeys::new(key);
+
+    let mut output: Vec<u8> = Vec::new();
+    for (i, sector) in image.chunks(sector_size).enumerate() {
+        let mut encrypted = keys.encrypt_sector(sector, first_sector + i as u128);
+        output.append(&mut encrypted);
+    }
+    output
+}
+
+pub fn xts_decrypt(image: Vec<u8>, key: Vec<u8>, sector_size: usize, first_sector: u128) -> Vec<u8> {
+    let keys = XtsKeys::new(key);
+
+    let mut output: Vec<u8> = Vec::new();
+    for (i, sector) in image.chunks(sector_size).enumerate() {
+        let mut decrypted = keys.decrypt_sector(sector, first_sector + i as u128);
+        output.append(&mut decrypted);
+    }
+    output
+}
+
+/* ----------- XTS FUNCTIONS ------------ */
+struct XtsKeys {
+    data_cipher: Aes,
+    tweak_cipher: Aes,
+}
+
+impl XtsKeys {
+    fn new(mut key: Vec<u8>) -> XtsKeys {
+        if key.len() != 32 && key.len() != 64 {
+            panic!("Invalid key length");
+        }
+
+        let tweak_key = key.split_off(key.len() / 2);
+
+        XtsKeys {
+            data_cipher: Aes::new(key),
+            tweak_cipher: Aes::new(tweak_key),
+        }
+    }
+
+    // the sector number is encoded little endian and encrypted under Key2
+    fn initial_tweak(&self, sector: u128) -> Vec<u8> {
+        let block = sector.to_le_bytes().to_vec();
+        self.tweak_cipher.encrypt_block(block)
+    }
+
+    fn encrypt_block(&self, block: &[u8], tweak: &[u8]) -> Vec<u8> {
+        let input = xor(block, tweak);
+        let output = self.data_cipher.encrypt_block(input);
+        xor(&output, tweak)
+    }
+
+    fn decrypt_block(&self, block: &[u8], tweak: &[u8]) -> Vec<u8> {
+        let input = xor(block, tweak);
+        let output = self.data_cipher.decrypt_block(input);
+        xor(&output, tweak)
+    }
+
+    fn encrypt_sector(&self, data: &[u8], sector: u128) -> Vec<u8> {
+        if data.len() < 16 {
+            panic!("Invalid sector length");
+        }
+
+        let remainder = data.len() % 16;
+        // the last full block takes part in the stealing if there is a partial block
+        let num_blocks = data.len() / 16 - (remainder != 0) as usize;
+
+        let mut tweak = self.initial_tweak(sector);
+        let mut cipher_text: Vec<u8> = Vec::new();
+
+        for i in 0..num_blocks {
+            let mut encrypted = self.encrypt_block(&data[i * 16..(i + 1) * 16], &tweak);
+            cipher_text.append(&mut encrypted);
+            mul_alpha(&mut tweak);
+        }
+
+        if remainder != 0 {
+            let start = num_blocks * 16;
+
+            let stolen = self.encrypt_block(&data[start..start + 16], &tweak);
+            mul_alpha(&mut tweak);
+
+            // fill the partial block with the tail of the previous ciphertext block
+            let mut block = data[start + 16..].to_vec();
+            block.extend_from_slice(&stolen[remainder..]);
+
+            let mut encrypted = self.encrypt_block(&block, &tweak);
+            cipher_text.append(&mut encrypted);
+            cipher_text.extend_from_slice(&stolen[..remainder]);
+        }
+
+        cipher_text
+    }
+
+    fn decrypt_sector(&self, data: &[u8], sector: u128) -> Vec<u8> {
+        if data.len() < 16 {
+            panic!("Invalid sector length");
+        }
+
+        let remainder = data.len() % 16;
+        let num_blocks = data.len() / 16 - (remainder != 0) as usize;
+
+        let mut tweak = self.initial_tweak(sector);
+        let mut plain_text: Vec<u8> = Vec::new();
+
+        for i in 0..num_blocks {
+            let mut decrypted = self.decrypt_block(&data[i * 16..(i + 1) * 16], &tweak);
+            plain_text.append(&mut decrypted);
+            mul_alpha(&mut tweak);
+        }
+
+        if remainder != 0 {
+            let start = num_blocks * 16;
+
+            // the second to last block was encrypted with the next tweak
+            let mut next_tweak = tweak.clone();
+            mul_alpha(&mut next_tweak);
+            let stolen = self.decrypt_block(&data[start..start + 16], &next_tweak);
+
+            let mut block = data[start + 16..].to_vec();
+            block.extend_from_slice(&stolen[remainder..]);
+
+            let mut decrypted = self.decrypt_block(&block, &tweak);
+            plain_text.append(&mut decrypted);
+            plain_text.extend_from_slice(&stolen[..remainder]);
+        }
+
+        plain_text
+    }
+}
+
+// multiply the tweak by the primitive element x of GF(2^128),
+// bytes are little endian so the carry moves towards the end
+fn mul_alpha(tweak: &mut [u8]) {
+    let mut carry = 0;
+    for byte in tweak.iter_mut() {
+        let next_carry = *byte >> 7;
+        *byte = (*byte << 1) | carry;
+        carry = next_carry;
+    }
+    if carry != 0 {
+        tweak[0] ^= 0x87;
+    }
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key1: &str, key2: &str) -> Vec<u8> {
+        let mut key = hex::decode(key1).unwrap();
+        key.append(&mut hex::decode(key2).unwrap());
+        key
+    }
+
+    fn check_vector(key: Vec<u8>, sector: u128, plain_text: Vec<u8>, expected: &str) {
+        let expected = hex::decode(expected).unwrap();
+
+        let encrypted = xts_encrypt_sector(plain_text.clone(), key.clone(), sector);
+        assert_eq!(encrypted, expected);
+
+        let decrypted = xts_decrypt_sector(encrypted, key, sector);
+        assert_eq!(decrypted, plain_text);
+    }
+
+    #[test]
+    fn ieee_1619_vector_1() {
+        check_vector(
+            vec![0; 32],
+            0,
+            vec![0; 32],
+            "917cf69ebd68b2ec9b9fe9a3eadda692cd43d2f59598ed858c02c2652fbf922e",
+        );
+    }
+
+    #[test]
+    fn ieee_1619_vector_2() {
+        check_vector(
+            key("11111111111111111111111111111111", "22222222222222222222222222222222"),
+            0x3333333333,
+            vec![0x44; 32],
+            "c454185e6a16936e39334038acef838bfb186fff7480adc4289382ecd6d394f0",
+        );
+    }
+
+    #[test]
+    fn ieee_1619_vector_3() {
+        check_vector(
+            key("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0", "22222222222222222222222222222222"),
+            0x3333333333,
+            vec![0x44; 32],
+            "af85336b597afc1a900b2eb21ec949d292df4c047e0b21532186a5971a227a89",
+        );
+    }
+
+    // vectors 4 and 10 are 512 byte sectors, each block only depends on its
+    // tweak so the first 64 bytes are checked
+    #[test]
+    fn ieee_1619_vector_4_prefix() {
+        check_vector(
+            key("27182818284590452353602874713526", "31415926535897932384626433832795"),
+            0,
+            (0..64).collect(),
+            "27a7479befa1d476489f308cd4cfa6e2a96e4bbe3208ff25287dd3819616e89c\
+             c78cf7f5e543445f8333d8fa7f56000005279fa5d8b5e4ad40e736ddb4d35412",
+        );
+    }
+
+    #[test]
+    fn ieee_1619_vector_10_prefix() {
+        check_vector(
+            key(
+                "2718281828459045235360287471352662497757247093699959574966967627",
+                "3141592653589793238462643383279502884197169399375105820974944592",
+            ),
+            0xff,
+            (0..64).collect(),
+            "1c3b3a102f770386e4836c99e370cf9bea00803f5e482357a4ae12d414a3e63b\
+             5d31e276f8fe4a8d66b317f9ac683f44680a86ac35adfc3345befecb4bb188fd",
+        );
+    }
+
+    #[test]
+    fn ieee_1619_ciphertext_stealing() {
+        let key = key("fffefdfcfbfaf9f8f7f6f5f4f3f2f1f0", "bfbebdbcbbbab9b8b7b6b5b4b3b2b1b0");
+        let vectors = [
+            (17, "6c1625db4671522d3d7599601de7ca09ed"),
+            (18, "d069444b7a7e0cab09e24447d24deb1fedbf"),
+            (19, "e5df1351c0544ba1350b3363cd8ef4beedbf9d"),
+            (20, "9d84c813f719aa2c7be3f66171c7c5c2edbf9dac"),
+        ];
+
+        for (length, expected) in vectors {
+            check_vector(key.clone(), 0x123456789a, (0..length).collect(), expected);
+        }
+    }
+
+    #[test]
+    fn image_round_trip() {
+        let key = crate::key_gen(256);
+        // three full sectors and a short final one
+        let image: Vec<u8> = (0..3 * 512 + 100).map(|i| (i % 251) as u8).collect();
+
+        let encrypted = xts_encrypt(image.clone(), key.clone(), 512, 7);
+        assert_eq!(encrypted.len(), image.len());
+
+        // each sector can be decrypted on its own
+        let sector = xts_decrypt_sector(encrypted[512..1024].to_vec(), key.clone(), 8);
+        assert_eq!(sector, image[512..1024]);
+
+        assert_eq!(xts_decrypt(encrypted, key, 512, 7), image);
+    }
+}
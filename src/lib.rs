@@ -1,7 +1,8 @@
+pub mod attacks;
 pub mod crypto;
+pub mod visualize;
 
 use rand::Rng;
-use hex;
 
 pub fn key_gen(size: u16) -> Vec<u8>{
     if ![128, 192, 256].contains(&size) {
@@ -18,16 +19,84 @@ pub fn key_gen(size: u16) -> Vec<u8>{
     key
 }
 
+pub fn iv_gen() -> Vec<u8> {
+    let mut iv = Vec::new();
+
+    let mut rng = rand::thread_rng();
+
+    for _ in 0..16 {
+        iv.push(rng.gen());
+    }
+
+    iv
+}
+
 pub fn encrypt(plaintext: String) -> (String, String) {
     let key = key_gen(256);
 
     let ciphertext = crypto::aes_encrypt(plaintext.as_bytes().to_vec().clone(), key.clone());
 
-    return (hex::encode(ciphertext), hex::encode(key));
+    (hex::encode(ciphertext), hex::encode(key))
+}
+
+// fails on input that isn't hex or a key of the wrong size, and usually on a
+// wrong key or damaged ciphertext, which shows up as bad padding or bad UTF-8
+pub fn decrypt(ciphertext: String, key: String) -> Result<String, crypto::Error> {
+    let ciphertext = hex::decode(ciphertext).map_err(|_| crypto::Error::InvalidHex)?;
+    let key = hex::decode(key).map_err(|_| crypto::Error::InvalidHex)?;
+    if ![16, 24, 32].contains(&key.len()) {
+        return Err(crypto::Error::InvalidKey);
+    }
+
+    let plaintext = crypto::aes_decrypt(ciphertext, key)?;
+
+    String::from_utf8(plaintext).map_err(|_| crypto::Error::InvalidUtf8)
 }
 
-pub fn decrypt(ciphertext: String, key: String) -> String {
-    let plaintext = crypto::aes_decrypt(hex::decode(ciphertext).unwrap(), hex::decode(key).unwrap());
 
-    return String::from_utf8(plaintext).unwrap();
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decrypt_round_trip() {
+        let (ciphertext, key) = encrypt(String::from("hello"));
+        assert_eq!(decrypt(ciphertext, key), Ok(String::from("hello")));
+    }
+
+    #[test]
+    fn decrypt_rejects_bad_hex() {
+        let (ciphertext, key) = encrypt(String::from("hello"));
+        assert_eq!(decrypt(String::from("zz"), key), Err(crypto::Error::InvalidHex));
+        assert_eq!(decrypt(ciphertext, String::from("0g")), Err(crypto::Error::InvalidHex));
+    }
+
+    #[test]
+    fn decrypt_rejects_bad_key_length() {
+        let (ciphertext, _) = encrypt(String::from("hello"));
+        assert_eq!(decrypt(ciphertext, String::from("00112233")), Err(crypto::Error::InvalidKey));
+    }
+
+    #[test]
+    fn decrypt_rejects_bad_padding() {
+        // a block ending in 0xaa isn't PKCS#7 padded
+        let key = key_gen(128);
+        let ciphertext = crypto::aes_encrypt_with_padding(vec![0xaa; 16], key.clone(), &crypto::padding::ZeroPadding);
+        assert_eq!(
+            decrypt(hex::encode(ciphertext), hex::encode(key)),
+            Err(crypto::Error::InvalidPadding)
+        );
+    }
+
+    #[test]
+    fn decrypt_rejects_invalid_utf8() {
+        let key = key_gen(128);
+        let ciphertext = crypto::aes_encrypt(vec![0xff, 0xfe], key.clone());
+        assert_eq!(
+            decrypt(hex::encode(ciphertext), hex::encode(key)),
+            Err(crypto::Error::InvalidUtf8)
+        );
+    }
 }
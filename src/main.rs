@@ -1,6 +1,94 @@
+use std::env;
+use std::fs;
 use std::io::{self, Write};
+use std::path::Path;
+use std::process;
+
+use lock_talk::visualize::html;
+use lock_talk::visualize::image::{self, Image};
+use lock_talk::visualize::tui;
 
 fn main(){
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.first().map(String::as_str) {
+        None => interactive(),
+        Some("ecb-image") => ecb_image(&args[1..]),
+        Some("tui") => tui(&args[1..]),
+        Some("explain") => explain(&args[1..]),
+        Some(command) => usage(&format!("Unknown command: {}", command)),
+    }
+}
+
+// lock_talk ecb-image <input> <output> [key-hex]
+fn ecb_image(args: &[String]) {
+    if args.len() < 2 || args.len() > 3 {
+        usage("ecb-image takes an input, an output and an optional key");
+    }
+
+    let key = match args.get(2) {
+        Some(key) => hex::decode(key).unwrap_or_else(|_| usage("The key must be hex")),
+        None => lock_talk::key_gen(128),
+    };
+    if ![16, 24, 32].contains(&key.len()) {
+        usage("The key must be 128, 192 or 256 bits");
+    }
+
+    let input = Image::read(Path::new(&args[0])).unwrap_or_else(|error| usage(&error.to_string()));
+    let comparison = image::ecb_comparison(&input, key.clone());
+    comparison.write(Path::new(&args[1])).unwrap_or_else(|error| usage(&error.to_string()));
+
+    println!("Wrote original | ECB | CBC | CTR to {}", args[1]);
+    println!("Key: {}", hex::encode(key));
+}
+
+// lock_talk tui [input-hex] [key-hex], defaults to the FIPS-197 example
+fn tui(args: &[String]) {
+    if args.len() > 2 {
+        usage("tui takes an optional input block and key");
+    }
+
+    let (input, key) = block_and_key(args);
+    tui::run(input, key).unwrap_or_else(|error| usage(&error.to_string()));
+}
+
+// lock_talk explain --html <output.html> [input-hex] [key-hex]
+fn explain(args: &[String]) {
+    if args.len() < 2 || args.len() > 4 || args[0] != "--html" {
+        usage("explain takes --html, an output file and an optional input block and key");
+    }
+
+    let (input, key) = block_and_key(&args[2..]);
+    fs::write(&args[1], html::explain_html(input, key)).unwrap_or_else(|error| usage(&error.to_string()));
+    println!("Wrote walkthrough to {}", args[1]);
+}
+
+// optional input block and key in hex, defaults to the FIPS-197 example
+fn block_and_key(args: &[String]) -> (Vec<u8>, Vec<u8>) {
+    let input = hex::decode(args.first().map_or("3243f6a8885a308d313198a2e0370734", String::as_str))
+        .unwrap_or_else(|_| usage("The input must be hex"));
+    let key = hex::decode(args.get(1).map_or("2b7e151628aed2a6abf7158809cf4f3c", String::as_str))
+        .unwrap_or_else(|_| usage("The key must be hex"));
+
+    if input.len() != 16 {
+        usage("The input must be one 128 bit block");
+    }
+    if ![16, 24, 32].contains(&key.len()) {
+        usage("The key must be 128, 192 or 256 bits");
+    }
+    (input, key)
+}
+
+fn usage(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("Usage: lock_talk");
+    eprintln!("       lock_talk ecb-image <input.ppm|png> <output.ppm|png> [key-hex]");
+    eprintln!("       lock_talk tui [input-hex] [key-hex]");
+    eprintln!("       lock_talk explain --html <output.html> [input-hex] [key-hex]");
+    process::exit(1);
+}
+
+fn interactive() {
     print!("Encrypt or Decrypt: (e/d): ");
     io::stdout().flush().expect("Failed to flush stdout");
 
@@ -52,11 +140,13 @@ fn main(){
 
         let trimmed_key = key.trim();
 
-        let plaintext = lock_talk::decrypt(
-            trimmed_ciphertext.to_string(), 
-            trimmed_key.to_string());
-
-        println!("Decrypted: {}", plaintext);
+        match lock_talk::decrypt(trimmed_ciphertext.to_string(), trimmed_key.to_string()) {
+            Ok(plaintext) => println!("Decrypted: {}", plaintext),
+            Err(error) => {
+                eprintln!("Decryption failed: {}", error);
+                process::exit(1);
+            }
+        }
 
     }
     
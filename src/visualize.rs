@@ -0,0 +1,5 @@
+pub mod avalanche;
+pub mod html;
+pub mod image;
+pub mod rounds;
+pub mod tui;
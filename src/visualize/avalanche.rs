@@ -0,0 +1,375 @@
+use std::fmt::Write;
+
+use crate::crypto::aes;
+
+// shades from no change to every flip changing the byte
+const SHADES: &[u8] = b" .:-=+*#%@";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flip {
+    PlainText,
+    Key,
+}
+
+// how far single bit flips spread through the rounds of one encryption
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    pub num_rounds: u8,
+    pub flip: Flip,
+    // distances[bit][round], Hamming distance from the unflipped state after
+    // that round, round 0 is after the whitening key
+    pub distances: Vec<Vec<u32>>,
+    // byte_changes[round][byte], how many of the flips changed that state byte
+    pub byte_changes: Vec<[u32; 16]>,
+}
+
+// flips every bit of the plaintext or key in turn and compares every round
+// against the unflipped encryption, num_rounds can be anything up to the full count
+pub fn avalanche(plain_text: Vec<u8>, key: Vec<u8>, num_rounds: u8, final_mix_columns: bool, flip: Flip) -> Heatmap {
+    check_parameters(plain_text.len(), key.len(), num_rounds);
+
+    let reference = states(plain_text.clone(), key.clone(), num_rounds, final_mix_columns);
+    let input_bits = match flip {
+        Flip::PlainText => plain_text.len() * 8,
+        Flip::Key => key.len() * 8,
+    };
+
+    let mut distances: Vec<Vec<u32>> = Vec::new();
+    let mut byte_changes = vec![[0; 16]; num_rounds as usize + 1];
+
+    for bit in 0..input_bits {
+        let flipped = match flip {
+            Flip::PlainText => states(flip_bit(&plain_text, bit), key.clone(), num_rounds, final_mix_columns),
+            Flip::Key => states(plain_text.clone(), flip_bit(&key, bit), num_rounds, final_mix_columns),
+        };
+
+        let mut row: Vec<u32> = Vec::new();
+        for (round, (reference, flipped)) in reference.iter().zip(&flipped).enumerate() {
+            row.push(hamming_distance(reference, flipped));
+            for (byte, (left, right)) in reference.iter().zip(flipped).enumerate() {
+                if left != right {
+                    byte_changes[round][byte] += 1;
+                }
+            }
+        }
+        distances.push(row);
+    }
+
+    Heatmap {
+        num_rounds,
+        flip,
+        distances,
+        byte_changes,
+    }
+}
+
+impl Heatmap {
+    // average number of state bits changed after each round
+    pub fn mean_distances(&self) -> Vec<f64> {
+        (0..=self.num_rounds as usize)
+            .map(|round| {
+                let total: u32 = self.distances.iter().map(|row| row[round]).sum();
+                total as f64 / self.distances.len() as f64
+            })
+            .collect()
+    }
+
+    // one row per flipped bit, one column per round
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("bit");
+        for round in 0..=self.num_rounds {
+            write!(csv, ",round_{}", round).unwrap();
+        }
+        csv.push('\n');
+
+        for (bit, row) in self.distances.iter().enumerate() {
+            write!(csv, "{}", bit).unwrap();
+            for distance in row {
+                write!(csv, ",{}", distance).unwrap();
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> String {
+        let flip = match self.flip {
+            Flip::PlainText => "plain_text",
+            Flip::Key => "key",
+        };
+        let mean_distances: Vec<String> = self.mean_distances().iter().map(|mean| format!("{:.4}", mean)).collect();
+        let distances: Vec<String> = self.distances.iter().map(|row| json_array(row)).collect();
+        let byte_changes: Vec<String> = self.byte_changes.iter().map(|round| json_array(round)).collect();
+
+        format!(
+            "{{\"num_rounds\":{},\"flip\":\"{}\",\"mean_distances\":[{}],\"distances\":[{}],\"byte_changes\":[{}]}}",
+            self.num_rounds,
+            flip,
+            mean_distances.join(","),
+            distances.join(","),
+            byte_changes.join(","),
+        )
+    }
+
+    // a 4x4 grid per round laid out like the state, darker where more flips reached
+    pub fn render(&self) -> String {
+        let flips = self.distances.len() as u32;
+        let mean_distances = self.mean_distances();
+        let mut output = String::new();
+
+        for (round, changes) in self.byte_changes.iter().enumerate() {
+            writeln!(output, "round {:>2}  mean distance {:>6.2} / 128", round, mean_distances[round]).unwrap();
+            for row in 0..4 {
+                output.push_str("  ");
+                for column in 0..4 {
+                    let shade = shade(changes[column * 4 + row], flips);
+                    write!(output, "{}{}", shade, shade).unwrap();
+                }
+                output.push('\n');
+            }
+        }
+        output
+    }
+}
+
+/* ----------- STRICT AVALANCHE CRITERION ------------ */
+// every output bit should flip with probability 1/2 whenever any single input bit flips
+#[derive(Debug, Clone, PartialEq)]
+pub struct SacSummary {
+    pub num_rounds: u8,
+    pub samples: usize,
+    // probabilities[input bit][output bit] that the output bit flipped
+    pub probabilities: Vec<Vec<f64>>,
+    pub mean: f64,
+    pub max_deviation: f64,
+}
+
+// estimates the SAC matrix over random plaintexts and keys
+pub fn strict_avalanche(key_bits: u16, num_rounds: u8, final_mix_columns: bool, samples: usize) -> SacSummary {
+    check_parameters(16, key_bits as usize / 8, num_rounds);
+
+    let mut counts = vec![vec![0u32; 128]; 128];
+    for _ in 0..samples {
+        let key = crate::key_gen(key_bits);
+        let plain_text = crate::iv_gen();
+        let reference = output(plain_text.clone(), key.clone(), num_rounds, final_mix_columns);
+
+        for (input_bit, row) in counts.iter_mut().enumerate() {
+            let flipped = output(flip_bit(&plain_text, input_bit), key.clone(), num_rounds, final_mix_columns);
+            for (output_bit, count) in row.iter_mut().enumerate() {
+                if get_bit(&reference, output_bit) != get_bit(&flipped, output_bit) {
+                    *count += 1;
+                }
+            }
+        }
+    }
+
+    let probabilities: Vec<Vec<f64>> = counts
+        .iter()
+        .map(|row| row.iter().map(|&count| count as f64 / samples as f64).collect())
+        .collect();
+    let entries = probabilities.iter().flatten();
+    let mean = entries.clone().sum::<f64>() / (128.0 * 128.0);
+    let max_deviation = entries.map(|probability| (probability - 0.5).abs()).fold(0.0, f64::max);
+
+    SacSummary {
+        num_rounds,
+        samples,
+        probabilities,
+        mean,
+        max_deviation,
+    }
+}
+
+// one summary for every round count from 1 up to the full cipher
+pub fn strict_avalanche_by_round(key_bits: u16, samples: usize) -> Vec<SacSummary> {
+    let full_rounds = aes::num_rounds(key_bits as usize / 32);
+    (1..=full_rounds)
+        .map(|num_rounds| strict_avalanche(key_bits, num_rounds, false, samples))
+        .collect()
+}
+
+impl SacSummary {
+    // share of the input/output pairs within tolerance of 1/2
+    pub fn fraction_within(&self, tolerance: f64) -> f64 {
+        let within = self
+            .probabilities
+            .iter()
+            .flatten()
+            .filter(|probability| (*probability - 0.5).abs() <= tolerance)
+            .count();
+        within as f64 / (128.0 * 128.0)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("input_bit");
+        for output_bit in 0..128 {
+            write!(csv, ",out_{}", output_bit).unwrap();
+        }
+        csv.push('\n');
+
+        for (input_bit, row) in self.probabilities.iter().enumerate() {
+            write!(csv, "{}", input_bit).unwrap();
+            for probability in row {
+                write!(csv, ",{:.4}", probability).unwrap();
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    // input bytes down, output bytes across, darker is further from 1/2
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        writeln!(
+            output,
+            "{} rounds, {} samples: mean {:.4}, max deviation {:.4}",
+            self.num_rounds, self.samples, self.mean, self.max_deviation
+        )
+        .unwrap();
+
+        for input_byte in 0..16 {
+            output.push_str("  ");
+            for output_byte in 0..16 {
+                let mut deviation = 0.0;
+                for input_bit in input_byte * 8..input_byte * 8 + 8 {
+                    for output_bit in output_byte * 8..output_byte * 8 + 8 {
+                        deviation += (self.probabilities[input_bit][output_bit] - 0.5).abs();
+                    }
+                }
+                // a mean deviation of 1/2 is an output bit that never or always flips
+                let shade = shade((deviation / 64.0 * 2.0 * 1000.0) as u32, 1000);
+                write!(output, "{}{}", shade, shade).unwrap();
+            }
+            output.push('\n');
+        }
+        output
+    }
+}
+
+/* ----------- HELPERS ------------ */
+fn check_parameters(block_length: usize, key_length: usize, num_rounds: u8) {
+    if block_length != 16 {
+        panic!("Invalid block length");
+    }
+    if ![16, 24, 32].contains(&key_length) {
+        panic!("Invalid key length");
+    }
+    if num_rounds == 0 || num_rounds > aes::num_rounds(key_length / 4) {
+        panic!("Invalid number of rounds");
+    }
+}
+
+fn states(plain_text: Vec<u8>, key: Vec<u8>, num_rounds: u8, final_mix_columns: bool) -> Vec<Vec<u8>> {
+    aes::round_states(plain_text, num_rounds, &aes::key_expansion(key), final_mix_columns)
+}
+
+fn output(plain_text: Vec<u8>, key: Vec<u8>, num_rounds: u8, final_mix_columns: bool) -> Vec<u8> {
+    aes::reduced_cipher(plain_text, num_rounds, &aes::key_expansion(key), final_mix_columns)
+}
+
+// bit 0 is the most significant bit of the first byte
+fn flip_bit(bytes: &[u8], bit: usize) -> Vec<u8> {
+    let mut flipped = bytes.to_vec();
+    flipped[bit / 8] ^= 0x80 >> (bit % 8);
+    flipped
+}
+
+fn get_bit(bytes: &[u8], bit: usize) -> bool {
+    bytes[bit / 8] & (0x80 >> (bit % 8)) != 0
+}
+
+fn hamming_distance(left: &[u8], right: &[u8]) -> u32 {
+    left.iter().zip(right).map(|(l, r)| (l ^ r).count_ones()).sum()
+}
+
+fn shade(count: u32, total: u32) -> char {
+    let level = (count as usize * (SHADES.len() - 1)).div_ceil(total.max(1) as usize);
+    SHADES[level.min(SHADES.len() - 1)] as char
+}
+
+fn json_array(values: &[u32]) -> String {
+    let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const INPUT: &str = "3243f6a8885a308d313198a2e0370734";
+
+    fn full_heatmap(flip: Flip) -> Heatmap {
+        avalanche(hex::decode(INPUT).unwrap(), hex::decode(KEY).unwrap(), 10, false, flip)
+    }
+
+    #[test]
+    fn plain_text_flips_spread_in_two_rounds() {
+        let heatmap = full_heatmap(Flip::PlainText);
+
+        assert_eq!(heatmap.distances.len(), 128);
+        assert!(heatmap.distances.iter().all(|row| row[0] == 1));
+
+        // one active byte becomes one active column
+        assert_eq!(heatmap.byte_changes[1].iter().sum::<u32>(), 128 * 4);
+        // and MixColumns is MDS, so after the second round every byte differs
+        assert_eq!(heatmap.byte_changes[2], [128; 16]);
+
+        let means = heatmap.mean_distances();
+        assert!((56.0..72.0).contains(&means[10]));
+    }
+
+    #[test]
+    fn key_flips() {
+        let heatmap = full_heatmap(Flip::Key);
+        assert!(heatmap.distances.iter().all(|row| row[0] == 1));
+        assert!((56.0..72.0).contains(&heatmap.mean_distances()[10]));
+
+        let heatmap = avalanche(vec![0; 16], vec![0; 32], 3, true, Flip::Key);
+        assert_eq!(heatmap.distances.len(), 256);
+        assert_eq!(heatmap.byte_changes.len(), 4);
+    }
+
+    #[test]
+    fn exports() {
+        let heatmap = avalanche(hex::decode(INPUT).unwrap(), hex::decode(KEY).unwrap(), 2, true, Flip::PlainText);
+
+        let csv = heatmap.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 129);
+        assert_eq!(lines[0], "bit,round_0,round_1,round_2");
+        assert!(lines[1].starts_with("0,1,"));
+
+        let json = heatmap.to_json();
+        assert!(json.starts_with("{\"num_rounds\":2,\"flip\":\"plain_text\",\"mean_distances\":[1.0000,"));
+        assert!(json.ends_with("]]}"));
+
+        let rendered = heatmap.render();
+        assert_eq!(rendered.lines().count(), 3 * 5);
+        assert!(rendered.lines().nth(1).unwrap().contains('.'));
+        // every byte is hit after two full rounds
+        assert_eq!(rendered.lines().nth(11).unwrap(), "  @@@@@@@@");
+    }
+
+    #[test]
+    fn sac_needs_enough_rounds() {
+        let one_round = strict_avalanche(128, 1, false, 32);
+        assert!(one_round.mean < 0.2);
+        assert!(one_round.max_deviation == 0.5);
+
+        let summaries = strict_avalanche_by_round(128, 48);
+        assert_eq!(summaries.len(), 10);
+
+        let full = &summaries[9];
+        assert!((full.mean - 0.5).abs() < 0.02);
+        assert!(full.fraction_within(0.25) > 0.99);
+        assert!(full.fraction_within(0.25) > summaries[0].fraction_within(0.25));
+
+        assert_eq!(full.to_csv().lines().count(), 129);
+        assert_eq!(full.render().lines().count(), 17);
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt::Write;
+
+use super::rounds::{self, Step};
+use crate::crypto::key_schedule;
+
+const CELL: usize = 34;
+const GRID: usize = CELL * 4;
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:60em;margin:2em auto;color:#222}\
+h1,h2{font-weight:normal}figure{display:inline-block;margin:.5em 1em .5em 0}\
+figcaption{font-size:.85em;color:#555}table{border-collapse:collapse;font-family:monospace}\
+td,th{border:1px solid #ccc;padding:.2em .5em;text-align:center}th{background:#f4f4f4}\
+code{font-size:1.1em}";
+
+// a self contained page walking through encrypting input under key and
+// decrypting it again, no scripts or external files
+pub fn explain_html(input: Vec<u8>, key: Vec<u8>) -> String {
+    let encryption = rounds::encryption_steps(input.clone(), key.clone());
+    let cipher_text = encryption.last().unwrap().state.clone();
+    let decryption = rounds::decryption_steps(cipher_text.clone(), key.clone());
+
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>").unwrap();
+    writeln!(html, "<html lang=\"en\"><head><meta charset=\"utf-8\">").unwrap();
+    writeln!(html, "<title>AES-{} walkthrough</title><style>{}</style></head><body>", key.len() * 8, STYLE).unwrap();
+    writeln!(html, "<h1>AES-{} walkthrough</h1>", key.len() * 8).unwrap();
+    writeln!(html, "<p>Input <code>{}</code><br>Key <code>{}</code></p>", hex::encode(&input), hex::encode(&key)).unwrap();
+
+    writeln!(html, "<h2>Key expansion</h2>").unwrap();
+    html.push_str(&key_expansion_table(&key));
+
+    writeln!(html, "<h2>Cipher</h2>").unwrap();
+    html.push_str(&transitions(&encryption));
+
+    writeln!(html, "<h2>Ciphertext</h2>").unwrap();
+    writeln!(html, "<p><code>{}</code></p>", hex::encode(&cipher_text)).unwrap();
+
+    writeln!(html, "<h2>Inverse cipher</h2>").unwrap();
+    html.push_str(&transitions(&decryption));
+
+    writeln!(html, "<h2>Recovered plaintext</h2>").unwrap();
+    writeln!(html, "<p><code>{}</code></p>", hex::encode(&decryption.last().unwrap().state)).unwrap();
+    writeln!(html, "</body></html>").unwrap();
+    html
+}
+
+/* ----------- STATE DIAGRAMS ------------ */
+// one figure per transformation, the state before and after with changed bytes filled
+fn transitions(steps: &[Step]) -> String {
+    let mut html = String::new();
+    for pair in steps.windows(2) {
+        let (before, after) = (&pair[0], &pair[1]);
+        writeln!(html, "<figure>{}", transition_svg(before, after)).unwrap();
+        writeln!(html, "<figcaption>Round {}: {}</figcaption></figure>", after.round, after.operation.name()).unwrap();
+    }
+    html
+}
+
+fn transition_svg(before: &Step, after: &Step) -> String {
+    let shows_key = after.operation == rounds::Operation::AddRoundKey;
+    let width = if shows_key { GRID * 3 + 80 } else { GRID * 2 + 60 };
+
+    let mut svg = String::new();
+    write!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"monospace\" font-size=\"13\">",
+        width,
+        GRID + 20,
+        width,
+        GRID + 20
+    )
+    .unwrap();
+
+    svg.push_str(&grid(&before.state, &before.state, 0, "#ffffff"));
+    write!(svg, "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">&#8594;</text>", GRID + 30, GRID / 2 + 5).unwrap();
+    svg.push_str(&grid(&after.state, &before.state, GRID + 60, "#ffe08a"));
+
+    if shows_key {
+        write!(svg, "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">&#8853;</text>", GRID * 2 + 70, GRID / 2 + 5).unwrap();
+        svg.push_str(&grid(&after.round_key, &after.round_key, GRID * 2 + 80, "#ffffff"));
+        write!(svg, "<text x=\"{}\" y=\"{}\">round key {}</text>", GRID * 2 + 80, GRID + 15, after.round).unwrap();
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+// a 4x4 grid in state order, bytes that differ from previous use highlight
+fn grid(state: &[u8], previous: &[u8], left: usize, highlight: &str) -> String {
+    let mut svg = String::new();
+    for column in 0..4 {
+        for row in 0..4 {
+            let position = column * 4 + row;
+            let fill = if state[position] != previous[position] { highlight } else { "#ffffff" };
+            let (x, y) = (left + column * CELL, row * CELL);
+            write!(
+                svg,
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"#888\"/>",
+                x, y, CELL, CELL, fill
+            )
+            .unwrap();
+            write!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{:02x}</text>",
+                x + CELL / 2,
+                y + CELL / 2 + 5,
+                state[position]
+            )
+            .unwrap();
+        }
+    }
+    svg
+}
+
+/* ----------- KEY EXPANSION ------------ */
+// laid out like the FIPS-197 appendix A tables
+fn key_expansion_table(key: &[u8]) -> String {
+    let mut html = String::from(
+        "<table><tr><th>i</th><th>temp</th><th>After RotWord()</th><th>After SubWord()</th>\
+         <th>Rcon[i/Nk]</th><th>After XOR with Rcon</th><th>w[i-Nk]</th><th>w[i]</th></tr>\n",
+    );
+
+    for step in key_schedule::key_expansion_trace(key.to_vec()) {
+        if step.temp.is_none() {
+            writeln!(html, "<tr><td>{}</td><td colspan=\"6\"></td><td>{}</td></tr>", step.i, hex::encode(&step.word)).unwrap();
+            continue;
+        }
+
+        let cell = |value: &Option<Vec<u8>>| value.as_ref().map_or(String::new(), hex::encode);
+        writeln!(
+            html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            step.i,
+            cell(&step.temp),
+            cell(&step.after_rot_word),
+            cell(&step.after_sub_word),
+            cell(&step.r_con),
+            cell(&step.after_xor_with_r_con),
+            cell(&step.w_i_minus_nk),
+            hex::encode(&step.word)
+        )
+        .unwrap();
+    }
+
+    html.push_str("</table>\n");
+    html
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::aes;
+
+    // FIPS-197 appendix B
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const INPUT: &str = "3243f6a8885a308d313198a2e0370734";
+
+    fn explain() -> String {
+        explain_html(hex::decode(INPUT).unwrap(), hex::decode(KEY).unwrap())
+    }
+
+    #[test]
+    fn page_is_self_contained() {
+        let html = explain();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.trim_end().ends_with("</body></html>"));
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("src="));
+        assert!(!html.contains("href="));
+    }
+
+    #[test]
+    fn diagrams_for_every_transition() {
+        let html = explain();
+
+        // 40 transformations each way
+        assert_eq!(html.matches("<svg").count(), 80);
+        assert_eq!(html.matches("<figcaption>Round 1: MixColumns</figcaption>").count(), 1);
+        assert_eq!(html.matches("<figcaption>Round 1: InvMixColumns</figcaption>").count(), 1);
+
+        // the ciphertext from aes::cipher and the plaintext coming back out
+        let key_schedule = aes::key_expansion(hex::decode(KEY).unwrap());
+        let cipher_text = aes::cipher(hex::decode(INPUT).unwrap(), 10, &key_schedule);
+        assert!(html.contains(&format!("<h2>Ciphertext</h2>\n<p><code>{}</code></p>", hex::encode(cipher_text))));
+        assert!(html.contains(&format!("<h2>Recovered plaintext</h2>\n<p><code>{}</code></p>", INPUT)));
+    }
+
+    #[test]
+    fn key_expansion_rows() {
+        let html = explain();
+
+        // FIPS-197 appendix A.1, i = 4 and i = 5
+        assert!(html.contains(
+            "<tr><td>4</td><td>09cf4f3c</td><td>cf4f3c09</td><td>8a84eb01</td><td>01000000</td>\
+             <td>8b84eb01</td><td>2b7e1516</td><td>a0fafe17</td></tr>"
+        ));
+        assert!(html.contains("<tr><td>5</td><td>a0fafe17</td><td></td><td></td><td></td><td></td><td>28aed2a6</td><td>88542cb1</td></tr>"));
+
+        // appendix A.3, the extra SubWord for 256 bit keys
+        let key = hex::decode("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").unwrap();
+        let html = explain_html(vec![0; 16], key);
+        assert!(html.contains("<tr><td>12</td><td>2067fcde</td><td></td><td>b785b01d</td><td></td><td></td><td>1f352c07</td><td>a8b09c1a</td></tr>"));
+        assert_eq!(html.matches("<svg").count(), 2 * (2 + 13 * 4 + 3 - 1));
+    }
+}
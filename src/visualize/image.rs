@@ -0,0 +1,283 @@
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::Path;
+
+use crate::crypto::block::{Aes, BlockMode, Ecb};
+use crate::crypto::cbc::Cbc;
+use crate::crypto::ctr::Ctr;
+use crate::crypto::encrypt_with_mode;
+use crate::crypto::padding::ZeroPadding;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageMode {
+    Ecb,
+    Cbc,
+    Ctr,
+}
+
+// 8 bit RGB pixels, row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<u8>) -> Image {
+        if pixel_length(width, height) != Some(pixels.len()) {
+            panic!("Invalid pixel data length");
+        }
+        Image { width, height, pixels }
+    }
+
+    // picks the format from the file contents
+    pub fn read(path: &Path) -> io::Result<Image> {
+        let bytes = fs::read(path)?;
+        if bytes.starts_with(&PNG_SIGNATURE) {
+            Image::from_png(&bytes)
+        } else {
+            Image::from_ppm(&bytes)
+        }
+    }
+
+    // picks the format from the file extension, PPM unless it ends in .png
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let is_png = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"));
+
+        if is_png {
+            fs::write(path, self.to_png()?)
+        } else {
+            fs::write(path, self.to_ppm())
+        }
+    }
+
+    /* ----------- PPM ------------ */
+    // binary P6 with a maximum value of 255
+    pub fn from_ppm(bytes: &[u8]) -> io::Result<Image> {
+        let mut position = 0;
+        let mut fields: Vec<usize> = Vec::new();
+
+        if !bytes.starts_with(b"P6") {
+            return Err(invalid_data("Not a binary PPM file"));
+        }
+        position += 2;
+
+        while fields.len() < 3 {
+            match bytes.get(position) {
+                Some(b'#') => {
+                    while position < bytes.len() && bytes[position] != b'\n' {
+                        position += 1;
+                    }
+                }
+                Some(byte) if byte.is_ascii_whitespace() => position += 1,
+                Some(byte) if byte.is_ascii_digit() => {
+                    let start = position;
+                    while position < bytes.len() && bytes[position].is_ascii_digit() {
+                        position += 1;
+                    }
+                    let field = std::str::from_utf8(&bytes[start..position]).unwrap();
+                    fields.push(field.parse().map_err(|_| invalid_data("Invalid PPM header"))?);
+                }
+                _ => return Err(invalid_data("Invalid PPM header")),
+            }
+        }
+
+        // a single whitespace byte separates the header from the pixels
+        position += 1;
+
+        let (width, height, max_value) = (fields[0], fields[1], fields[2]);
+        if max_value != 255 {
+            return Err(invalid_data("Only 8 bit PPM files are supported"));
+        }
+
+        let length = pixel_length(width, height).ok_or_else(|| invalid_data("PPM dimensions are too large"))?;
+        if bytes.len().saturating_sub(position) < length {
+            return Err(invalid_data("PPM pixel data is truncated"));
+        }
+
+        Ok(Image::new(width, height, bytes[position..position + length].to_vec()))
+    }
+
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut bytes = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        bytes.extend_from_slice(&self.pixels);
+        bytes
+    }
+
+    /* ----------- PNG ------------ */
+    pub fn from_png(bytes: &[u8]) -> io::Result<Image> {
+        let mut decoder = png::Decoder::new(Cursor::new(bytes));
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+        let mut reader = decoder.read_info().map_err(invalid_data)?;
+        let size = reader
+            .output_buffer_size()
+            .ok_or_else(|| invalid_data("PNG is too large"))?;
+        let mut buffer = vec![0; size];
+        let info = reader.next_frame(&mut buffer).map_err(invalid_data)?;
+        buffer.truncate(info.buffer_size());
+
+        // drop alpha and spread grey out to RGB
+        let pixels: Vec<u8> = match info.color_type {
+            png::ColorType::Rgb => buffer,
+            png::ColorType::Rgba => buffer.chunks(4).flat_map(|pixel| pixel[..3].to_vec()).collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|&grey| [grey; 3]).collect(),
+            png::ColorType::GrayscaleAlpha => buffer.chunks(2).flat_map(|pixel| [pixel[0]; 3]).collect(),
+            png::ColorType::Indexed => return Err(invalid_data("Unexpanded palette PNG")),
+        };
+
+        Ok(Image::new(info.width as usize, info.height as usize, pixels))
+    }
+
+    pub fn to_png(&self) -> io::Result<Vec<u8>> {
+        let mut bytes: Vec<u8> = Vec::new();
+
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&self.pixels).map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)?;
+
+        Ok(bytes)
+    }
+}
+
+/* ----------- ENCRYPTION ------------ */
+// encrypts the raw pixel bytes, the last partial block is zero padded and then
+// cut off again so the result is still a viewable image of the same size
+pub fn encrypt_image(image: &Image, key: Vec<u8>, mode: ImageMode, iv: Vec<u8>) -> Image {
+    let mut block_mode: Box<dyn BlockMode> = match mode {
+        ImageMode::Ecb => Box::new(Ecb::new(Aes::new(key))),
+        ImageMode::Cbc => Box::new(Cbc::new(Aes::new(key), iv)),
+        ImageMode::Ctr => Box::new(Ctr::new(Aes::new(key), iv)),
+    };
+
+    let mut pixels = encrypt_with_mode(image.pixels.clone(), block_mode.as_mut(), &ZeroPadding);
+    pixels.truncate(image.pixels.len());
+
+    Image::new(image.width, image.height, pixels)
+}
+
+// original, ECB, CBC and CTR next to each other
+pub fn ecb_comparison(image: &Image, key: Vec<u8>) -> Image {
+    let mut panels = vec![image.clone()];
+    for mode in [ImageMode::Ecb, ImageMode::Cbc, ImageMode::Ctr] {
+        panels.push(encrypt_image(image, key.clone(), mode, crate::iv_gen()));
+    }
+    side_by_side(&panels, 8)
+}
+
+// places the images left to right on a white background with gap pixels between them
+pub fn side_by_side(images: &[Image], gap: usize) -> Image {
+    let width = images.iter().map(|image| image.width).sum::<usize>() + gap * images.len().saturating_sub(1);
+    let height = images.iter().map(|image| image.height).max().unwrap_or(0);
+
+    let mut pixels = vec![0xff; pixel_length(width, height).expect("Image is too large")];
+    let mut left = 0;
+    for image in images {
+        for row in 0..image.height {
+            let source = &image.pixels[row * image.width * 3..(row + 1) * image.width * 3];
+            let start = (row * width + left) * 3;
+            pixels[start..start + source.len()].copy_from_slice(source);
+        }
+        left += image.width + gap;
+    }
+
+    Image::new(width, height, pixels)
+}
+
+// bytes of RGB data for the dimensions, None if that doesn't fit in a usize
+fn pixel_length(width: usize, height: usize) -> Option<usize> {
+    width.checked_mul(height)?.checked_mul(3)
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    // large flat bands of colour, the kind of picture ECB gives away
+    fn synthetic_image() -> Image {
+        let (width, height) = (64, 48);
+        let colours = [[0x20, 0x20, 0x20], [0xf0, 0xf0, 0xf0], [0xe0, 0x90, 0x10]];
+
+        let mut pixels: Vec<u8> = Vec::new();
+        for row in 0..height {
+            for column in 0..width {
+                let band = if column < 16 { 0 } else if row < 24 { 1 } else { 2 };
+                pixels.extend_from_slice(&colours[band]);
+            }
+        }
+        Image::new(width, height, pixels)
+    }
+
+    fn distinct_blocks(image: &Image) -> usize {
+        image.pixels.chunks(16).collect::<HashSet<_>>().len()
+    }
+
+    #[test]
+    fn ecb_leaks_structure() {
+        let image = synthetic_image();
+        let key = crate::key_gen(128);
+        let total_blocks = image.pixels.len() / 16;
+
+        let ecb = encrypt_image(&image, key.clone(), ImageMode::Ecb, Vec::new());
+        let cbc = encrypt_image(&image, key.clone(), ImageMode::Cbc, crate::iv_gen());
+        let ctr = encrypt_image(&image, key, ImageMode::Ctr, crate::iv_gen());
+
+        // ECB maps identical plaintext blocks to identical ciphertext blocks
+        assert_eq!(distinct_blocks(&ecb), distinct_blocks(&image));
+        assert!(distinct_blocks(&ecb) < total_blocks / 10);
+
+        assert_eq!(distinct_blocks(&cbc), total_blocks);
+        assert_eq!(distinct_blocks(&ctr), total_blocks);
+    }
+
+    #[test]
+    fn comparison_layout() {
+        let image = synthetic_image();
+        let comparison = ecb_comparison(&image, crate::key_gen(256));
+
+        assert_eq!(comparison.width, image.width * 4 + 8 * 3);
+        assert_eq!(comparison.height, image.height);
+        // the first panel is the untouched original
+        assert_eq!(comparison.pixels[..image.width * 3], image.pixels[..image.width * 3]);
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let image = synthetic_image();
+        let mut bytes = b"P6\n# a comment\n".to_vec();
+        bytes.extend_from_slice(&image.to_ppm()[3..]);
+
+        assert_eq!(Image::from_ppm(&bytes).unwrap(), image);
+        assert!(Image::from_ppm(b"P6 4 4 255\n").is_err());
+        assert!(Image::from_ppm(b"P3 1 1 255\n000").is_err());
+        // width * height * 3 overflows
+        let huge = format!("P6 {} 2 255\n", usize::MAX / 2);
+        assert!(Image::from_ppm(huge.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let image = synthetic_image();
+        let bytes = image.to_png().unwrap();
+
+        assert!(bytes.starts_with(&PNG_SIGNATURE));
+        assert_eq!(Image::from_png(&bytes).unwrap(), image);
+    }
+}
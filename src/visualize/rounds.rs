@@ -0,0 +1,296 @@
+use std::fmt::Write;
+
+use crossterm::style::Stylize;
+
+use crate::crypto::gf256::Gf256;
+use crate::crypto::{aes, sbox};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Input,
+    SubBytes,
+    ShiftRows,
+    MixColumns,
+    AddRoundKey,
+    InvSubBytes,
+    InvShiftRows,
+    InvMixColumns,
+}
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Input => "Input",
+            Operation::SubBytes => "SubBytes",
+            Operation::ShiftRows => "ShiftRows",
+            Operation::MixColumns => "MixColumns",
+            Operation::AddRoundKey => "AddRoundKey",
+            Operation::InvSubBytes => "InvSubBytes",
+            Operation::InvShiftRows => "InvShiftRows",
+            Operation::InvMixColumns => "InvMixColumns",
+        }
+    }
+}
+
+// the state right after one transformation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub round: usize,
+    pub operation: Operation,
+    pub state: Vec<u8>,
+    // the round key belonging to this round, from key_expansion
+    pub round_key: Vec<u8>,
+}
+
+/* ----------- TRACES ------------ */
+// every intermediate state of the cipher, starting with the input
+pub fn encryption_steps(input: Vec<u8>, key: Vec<u8>) -> Vec<Step> {
+    let (num_rounds, key_schedule) = schedule(&input, key);
+    let round_key = |round: usize| key_schedule[round * 4..round * 4 + 4].concat();
+
+    let mut steps = vec![Step {
+        round: 0,
+        operation: Operation::Input,
+        state: input.clone(),
+        round_key: round_key(0),
+    }];
+    let mut push = |round: usize, operation: Operation, state: &Vec<u8>| {
+        steps.push(Step {
+            round,
+            operation,
+            state: state.clone(),
+            round_key: round_key(round),
+        })
+    };
+
+    let mut state = aes::add_round_key(input, &key_schedule[0..4]);
+    push(0, Operation::AddRoundKey, &state);
+
+    for round in 1..=num_rounds {
+        state = aes::sub_bytes(state);
+        push(round, Operation::SubBytes, &state);
+        state = aes::shift_rows(state);
+        push(round, Operation::ShiftRows, &state);
+        // don't mix columns in final round
+        if round < num_rounds {
+            state = aes::mix_columns(state);
+            push(round, Operation::MixColumns, &state);
+        }
+        state = aes::add_round_key(state, &key_schedule[round * 4..round * 4 + 4]);
+        push(round, Operation::AddRoundKey, &state);
+    }
+    steps
+}
+
+// every intermediate state of the inverse cipher, rounds count down to 0
+pub fn decryption_steps(input: Vec<u8>, key: Vec<u8>) -> Vec<Step> {
+    let (num_rounds, key_schedule) = schedule(&input, key);
+    let round_key = |round: usize| key_schedule[round * 4..round * 4 + 4].concat();
+
+    let mut steps = vec![Step {
+        round: num_rounds,
+        operation: Operation::Input,
+        state: input.clone(),
+        round_key: round_key(num_rounds),
+    }];
+    let mut push = |round: usize, operation: Operation, state: &Vec<u8>| {
+        steps.push(Step {
+            round,
+            operation,
+            state: state.clone(),
+            round_key: round_key(round),
+        })
+    };
+
+    let mut state = aes::add_round_key(input, &key_schedule[num_rounds * 4..num_rounds * 4 + 4]);
+    push(num_rounds, Operation::AddRoundKey, &state);
+
+    for round in (0..num_rounds).rev() {
+        state = aes::inv_shift_rows(state);
+        push(round, Operation::InvShiftRows, &state);
+        state = aes::inv_sub_bytes(state);
+        push(round, Operation::InvSubBytes, &state);
+        state = aes::add_round_key(state, &key_schedule[round * 4..round * 4 + 4]);
+        push(round, Operation::AddRoundKey, &state);
+        if round > 0 {
+            state = aes::inv_mix_columns(state);
+            push(round, Operation::InvMixColumns, &state);
+        }
+    }
+    steps
+}
+
+fn schedule(input: &[u8], key: Vec<u8>) -> (usize, Vec<Vec<u8>>) {
+    if input.len() != 16 {
+        panic!("Invalid block length");
+    }
+    if ![16, 24, 32].contains(&key.len()) {
+        panic!("Invalid key length");
+    }
+    (aes::num_rounds(key.len() / 4) as usize, aes::key_expansion(key))
+}
+
+/* ----------- RENDERING ------------ */
+// one screen for steps[index], the bytes that changed since the previous
+// step are highlighted, or marked with a * when color is off
+pub fn render_step(steps: &[Step], index: usize, title: &str, color: bool) -> Vec<String> {
+    let step = &steps[index];
+    let previous = if index > 0 { &steps[index - 1].state } else { &step.state };
+
+    let mut lines = vec![
+        format!(
+            "{}   step {}/{}   round {}   {}",
+            title,
+            index,
+            steps.len() - 1,
+            step.round,
+            step.operation.name()
+        ),
+        String::new(),
+        format!("  {:<18}round key {}", "state", step.round),
+    ];
+
+    for row in 0..4 {
+        let mut line = String::from("  ");
+        for column in 0..4 {
+            let position = column * 4 + row;
+            let byte = format!("{:02x}", step.state[position]);
+            let changed = step.state[position] != previous[position];
+
+            match (changed, color) {
+                (true, true) => write!(line, "{} ", byte.reverse().bold()).unwrap(),
+                (true, false) => write!(line, "{}*", byte).unwrap(),
+                (false, _) => write!(line, "{} ", byte).unwrap(),
+            }
+            line.push(' ');
+        }
+        line.push_str("  ");
+        for column in 0..4 {
+            write!(line, "{:02x} ", step.round_key[column * 4 + row]).unwrap();
+        }
+        lines.push(line.trim_end().to_string());
+    }
+
+    lines.push(String::new());
+    lines.push(format!("  {}", hex::encode(&step.state)));
+
+    // how the first byte of the state was substituted
+    if step.operation == Operation::SubBytes {
+        lines.push(String::new());
+        lines.extend(sbox::explain(previous[0]).render().into_iter().map(|line| format!("  {}", line)));
+    }
+
+    // how the first byte of the state was mixed, row 0 of the matrix times column 0
+    let matrix = match step.operation {
+        Operation::MixColumns => Some(&aes::MIX_COLUMNS),
+        Operation::InvMixColumns => Some(&aes::INV_MIX_COLUMNS),
+        _ => None,
+    };
+    if let Some(matrix) = matrix {
+        lines.push(String::new());
+        lines.extend(explain_mix(matrix[0], &previous[0..4]).into_iter().map(|line| format!("  {}", line)));
+    }
+    lines
+}
+
+// each product of a matrix row and a column in GF(2^8), then their sum
+fn explain_mix(row: [u8; 4], column: &[u8]) -> Vec<String> {
+    let products: Vec<Gf256> = row
+        .iter()
+        .zip(column)
+        .map(|(&factor, &byte)| Gf256::new(factor) * Gf256::new(byte))
+        .collect();
+
+    let mut lines: Vec<String> = row
+        .iter()
+        .zip(column)
+        .zip(&products)
+        .map(|((factor, byte), product)| format!("{:02x} * {:02x} = {}", factor, byte, product))
+        .collect();
+    let terms: Vec<String> = products.iter().map(|product| product.to_string()).collect();
+    let sum = products.iter().fold(Gf256::new(0), |acc, &product| acc + product);
+    lines.push(format!("{} = {}", terms.join(" + "), sum));
+    lines
+}
+
+
+
+/* ----------- TESTING ------------ */
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // FIPS-197 appendix B
+    const KEY: &str = "2b7e151628aed2a6abf7158809cf4f3c";
+    const INPUT: &str = "3243f6a8885a308d313198a2e0370734";
+    const OUTPUT: &str = "3925841d02dc09fbdc118597196a0b32";
+
+    fn state_of(steps: &[Step], round: usize, operation: Operation) -> String {
+        let step = steps
+            .iter()
+            .find(|step| step.round == round && step.operation == operation)
+            .unwrap();
+        hex::encode(&step.state)
+    }
+
+    #[test]
+    fn encryption_matches_fips_197() {
+        let steps = encryption_steps(hex::decode(INPUT).unwrap(), hex::decode(KEY).unwrap());
+
+        // input, whitening, 9 rounds of 4 and a final round of 3
+        assert_eq!(steps.len(), 2 + 9 * 4 + 3);
+        assert_eq!(state_of(&steps, 0, Operation::AddRoundKey), "193de3bea0f4e22b9ac68d2ae9f84808");
+        assert_eq!(state_of(&steps, 1, Operation::SubBytes), "d42711aee0bf98f1b8b45de51e415230");
+        assert_eq!(state_of(&steps, 1, Operation::ShiftRows), "d4bf5d30e0b452aeb84111f11e2798e5");
+        assert_eq!(state_of(&steps, 1, Operation::MixColumns), "046681e5e0cb199a48f8d37a2806264c");
+        assert_eq!(hex::encode(&steps.last().unwrap().state), OUTPUT);
+        assert_eq!(hex::encode(&steps[5].round_key), "a0fafe1788542cb123a339392a6c7605");
+    }
+
+    #[test]
+    fn decryption_retraces_encryption() {
+        let steps = decryption_steps(hex::decode(OUTPUT).unwrap(), hex::decode(KEY).unwrap());
+        assert_eq!(steps.len(), 2 + 9 * 4 + 3);
+        assert_eq!(hex::encode(&steps.last().unwrap().state), INPUT);
+
+        // rounds are numbered by the round key, as in the FIPS-197 InvCipher
+        assert_eq!(state_of(&steps, 1, Operation::InvMixColumns), "d4bf5d30e0b452aeb84111f11e2798e5");
+        assert_eq!(state_of(&steps, 0, Operation::InvSubBytes), "193de3bea0f4e22b9ac68d2ae9f84808");
+
+        let steps = decryption_steps(crate::key_gen(256)[..16].to_vec(), crate::key_gen(256));
+        assert_eq!(steps.len(), 2 + 13 * 4 + 3);
+    }
+
+    #[test]
+    fn render_marks_changed_bytes() {
+        let steps = encryption_steps(hex::decode(INPUT).unwrap(), hex::decode(KEY).unwrap());
+        let lines = render_step(&steps, 3, "AES-128 encryption", false);
+
+        assert_eq!(lines[0], "AES-128 encryption   step 3/40   round 1   ShiftRows");
+        // row 0 doesn't move, the other rows rotate
+        assert_eq!(lines[3], "  d4  e0  b8  1e    a0 88 23 2a");
+        assert_eq!(lines[4], "  bf* b4* 41* 27*   fa 54 a3 6c");
+        assert_eq!(lines[8], "  d4bf5d30e0b452aeb84111f11e2798e5");
+
+        assert!(render_step(&steps, 0, "", false).iter().all(|line| !line.contains('*')));
+        assert!(render_step(&steps, 3, "", true)[4].contains("\u{1b}["));
+
+        // SubBytes shows the working for the first byte, FIPS-197 appendix B 19 -> d4
+        let lines = render_step(&steps, 2, "", false);
+        assert_eq!(lines[10], "  input        19 = x^4 + x^3 + 1");
+        assert_eq!(lines[15], "  xor 63       11010100 = d4");
+
+        // MixColumns shows row 0 of the matrix times column 0, d4 bf 5d 30 -> 04
+        let lines = render_step(&steps, 4, "", false);
+        assert_eq!(lines[10], "  02 * d4 = b3");
+        assert_eq!(lines[11], "  03 * bf = da");
+        assert_eq!(lines[14], "  b3 + da + 5d + 30 = 04");
+
+        // and InvMixColumns the inverse matrix, which takes 04 back to d4
+        let steps = decryption_steps(hex::decode(OUTPUT).unwrap(), hex::decode(KEY).unwrap());
+        let index = steps.iter().position(|step| step.round == 1 && step.operation == Operation::InvMixColumns).unwrap();
+        let lines = render_step(&steps, index, "", false);
+        assert_eq!(lines[10], "  0e * 04 = 38");
+        assert!(lines[14].ends_with(" = d4"));
+    }
+}
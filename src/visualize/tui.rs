@@ -0,0 +1,73 @@
+use std::io::{self, Write};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::{cursor, execute, queue, terminal};
+
+use super::rounds::{self, Step};
+
+const HELP: &str = "  <-/h back   ->/l/space forward   home/end   d switch encrypt/decrypt   q quit";
+
+// restores the terminal even if drawing fails part way
+struct RawMode;
+
+impl RawMode {
+    fn enter() -> io::Result<RawMode> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// steps through encrypting input under key, and decrypting the result
+pub fn run(input: Vec<u8>, key: Vec<u8>) -> io::Result<()> {
+    let key_bits = key.len() * 8;
+    let encryption = rounds::encryption_steps(input, key.clone());
+    let cipher_text = encryption.last().unwrap().state.clone();
+    let decryption = rounds::decryption_steps(cipher_text, key);
+
+    let titles = [format!("AES-{} encryption", key_bits), format!("AES-{} decryption", key_bits)];
+    let traces: [&[Step]; 2] = [&encryption, &decryption];
+
+    let _raw_mode = RawMode::enter()?;
+    let mut stdout = io::stdout();
+    let mut trace = 0;
+    let mut index = 0;
+
+    loop {
+        queue!(stdout, terminal::Clear(terminal::ClearType::All), cursor::MoveTo(0, 0))?;
+        for line in rounds::render_step(traces[trace], index, &titles[trace], true) {
+            // raw mode doesn't return the cursor on a newline
+            write!(stdout, "{}\r\n", line)?;
+        }
+        write!(stdout, "\r\n{}\r\n", HELP)?;
+        stdout.flush()?;
+
+        let last = traces[trace].len() - 1;
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') => index = (index + 1).min(last),
+                KeyCode::Left | KeyCode::Char('h') => index = index.saturating_sub(1),
+                KeyCode::Home => index = 0,
+                KeyCode::End => index = last,
+                KeyCode::Char('d') => {
+                    trace = 1 - trace;
+                    index = 0;
+                }
+                KeyCode::Char('q') | KeyCode::Esc => break,
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
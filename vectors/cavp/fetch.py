@@ -0,0 +1,34 @@
+# Downloads NIST's known answer and Monte Carlo response files for AES into
+# this directory, see README.md. The files are written as they come out of
+# the archives, nothing is changed:
+#
+#   python3 vectors/cavp/fetch.py
+#
+# Only needs the standard library.
+
+import io
+import os
+import urllib.request
+import zipfile
+
+OUT = os.path.dirname(os.path.abspath(__file__))
+BASE = 'https://csrc.nist.gov/CSRC/media/Projects/Cryptographic-Algorithm-Validation-Program/documents/aes/'
+ARCHIVES = ['KAT_AES.zip', 'aesmct.zip']
+
+
+def main():
+    for archive in ARCHIVES:
+        with urllib.request.urlopen(BASE + archive) as response:
+            data = response.read()
+        with zipfile.ZipFile(io.BytesIO(data)) as files:
+            for member in files.namelist():
+                name = os.path.basename(member)
+                if not name.endswith('.rsp'):
+                    continue
+                with open(os.path.join(OUT, name), 'wb') as file:
+                    file.write(files.read(member))
+                print(name)
+
+
+if __name__ == '__main__':
+    main()
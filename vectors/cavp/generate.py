@@ -0,0 +1,95 @@
+# Writes the multi block message (MMT) response files in this directory, see
+# README.md. The known answer and Monte Carlo files are NIST's own, fetch.py
+# downloads them.
+#
+# Needs the Python cryptography package, whose AES comes from OpenSSL:
+#
+#   python3 vectors/cavp/generate.py
+#
+# Inputs come from a seeded generator, running the script again gives the same
+# files.
+
+import os
+import random
+
+from cryptography.hazmat.primitives.ciphers import Cipher, algorithms, modes
+
+try:
+    # where newer versions keep CFB and OFB
+    from cryptography.hazmat.decrepit.ciphers import modes as legacy_modes
+except ImportError:
+    legacy_modes = modes
+
+OUT = os.path.dirname(os.path.abspath(__file__))
+MODES = ['ECB', 'CBC', 'CFB8', 'CFB128', 'OFB']
+
+
+def cipher_mode(mode, iv):
+    return {
+        'ECB': lambda: modes.ECB(),
+        'CBC': lambda: modes.CBC(iv),
+        'CFB8': lambda: legacy_modes.CFB8(iv),
+        'CFB128': lambda: legacy_modes.CFB(iv),
+        'OFB': lambda: legacy_modes.OFB(iv),
+    }[mode]()
+
+
+class Stream:
+    def __init__(self, mode, key, iv, encrypt):
+        cipher = Cipher(algorithms.AES(key), cipher_mode(mode, iv))
+        self.context = cipher.encryptor() if encrypt else cipher.decryptor()
+
+    def __call__(self, data):
+        return self.context.update(data)
+
+
+def unit(mode):
+    return 1 if mode == 'CFB8' else 16
+
+
+def random_bytes(rng, length):
+    return bytes(rng.getrandbits(8) for _ in range(length))
+
+
+def vector(mode, count, key, iv, first, second):
+    fields = [('COUNT', str(count)), ('KEY', key.hex())]
+    if mode != 'ECB':
+        fields.append(('IV', iv.hex()))
+    return fields + [first, second]
+
+
+def write(name, title, sections):
+    lines = ['# AESAVS style response file, see README.md', '# AESVS %s test data' % title,
+             '# State : Encrypt and Decrypt', '']
+    for direction, vectors in sections:
+        lines += ['[%s]' % direction, '']
+        for fields in vectors:
+            lines += ['%s = %s' % field for field in fields] + ['']
+    with open(os.path.join(OUT, name), 'w', newline='') as file:
+        file.write('\r\n'.join(lines))
+
+
+# ten messages of 1 to 10 units each way
+def multi_block(rng, mode, bits):
+    sections = []
+    for direction, names in (('ENCRYPT', ('PLAINTEXT', 'CIPHERTEXT')), ('DECRYPT', ('CIPHERTEXT', 'PLAINTEXT'))):
+        vectors = []
+        for count in range(10):
+            key = random_bytes(rng, bits // 8)
+            iv = random_bytes(rng, 16)
+            data = random_bytes(rng, unit(mode) * (count + 1))
+            output = Stream(mode, key, iv, direction == 'ENCRYPT')(data)
+            vectors.append(vector(mode, count, key, iv, (names[0], data.hex()), (names[1], output.hex())))
+        sections.append((direction, vectors))
+    return sections
+
+
+def main():
+    rng = random.Random(2024)
+    for mode in MODES:
+        for bits in (128, 192, 256):
+            write('%sMMT%d.rsp' % (mode, bits), 'MMT', multi_block(rng, mode, bits))
+
+
+if __name__ == '__main__':
+    main()